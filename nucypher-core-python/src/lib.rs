@@ -289,12 +289,22 @@ impl FromBackend<nucypher_core::TreasureMap> for TreasureMap {
 #[pymethods]
 impl TreasureMap {
     #[new]
+    #[args(
+        signer,
+        hrac,
+        policy_encrypting_key,
+        assigned_kfrags,
+        threshold,
+        "*",
+        expiration_epoch = "None"
+    )]
     pub fn new(
         signer: &Signer,
         hrac: &HRAC,
         policy_encrypting_key: &PublicKey,
         assigned_kfrags: BTreeMap<[u8; nucypher_core::Address::SIZE], (PublicKey, VerifiedKeyFrag)>,
         threshold: u8,
+        expiration_epoch: Option<u32>,
     ) -> Self {
         let assigned_kfrags_backend = assigned_kfrags
             .into_iter()
@@ -306,12 +316,13 @@ impl TreasureMap {
             })
             .collect::<Vec<_>>();
         Self {
-            backend: nucypher_core::TreasureMap::new(
+            backend: nucypher_core::TreasureMap::new_with_expiration(
                 &signer.backend,
                 &hrac.backend,
                 &policy_encrypting_key.backend,
                 assigned_kfrags_backend,
                 threshold,
+                expiration_epoch,
             ),
         }
     }
@@ -358,6 +369,15 @@ impl TreasureMap {
         self.backend.threshold
     }
 
+    #[getter]
+    fn expiration_epoch(&self) -> Option<u32> {
+        self.backend.expiration_epoch
+    }
+
+    fn is_expired(&self, now_epoch: u32) -> bool {
+        self.backend.is_expired(now_epoch)
+    }
+
     #[getter]
     fn policy_encrypting_key(&self) -> PublicKey {
         PublicKey {
@@ -563,6 +583,7 @@ impl ReencryptionResponse {
 
     pub fn verify(
         &self,
+        py: Python,
         capsules: Vec<Capsule>,
         alice_verifying_key: &PublicKey,
         ursula_verifying_key: &PublicKey,
@@ -573,15 +594,19 @@ impl ReencryptionResponse {
             .iter()
             .map(|capsule| capsule.backend)
             .collect::<Vec<_>>();
-        let vcfrags_backend = self
-            .backend
-            .verify(
-                &capsules_backend,
-                &alice_verifying_key.backend,
-                &ursula_verifying_key.backend,
-                &policy_encrypting_key.backend,
-                &bob_encrypting_key.backend,
-            )
+        let backend = &self.backend;
+        // Verifying the capsule frags is CPU-bound and does not touch Python objects,
+        // so other Python threads can run in the meantime.
+        let vcfrags_backend = py
+            .allow_threads(|| {
+                backend.verify(
+                    &capsules_backend,
+                    &alice_verifying_key.backend,
+                    &ursula_verifying_key.backend,
+                    &policy_encrypting_key.backend,
+                    &bob_encrypting_key.backend,
+                )
+            })
             .map_err(|_err| PyValueError::new_err("ReencryptionResponse verification failed"))?;
         Ok(vcfrags_backend
             .iter()
@@ -695,21 +720,39 @@ impl FromBackend<nucypher_core::RevocationOrder> for RevocationOrder {
 #[pymethods]
 impl RevocationOrder {
     #[new]
+    #[args(
+        signer,
+        staking_provider_address,
+        encrypted_kfrag,
+        "*",
+        issued_at = "None"
+    )]
     pub fn new(
         signer: &Signer,
         staking_provider_address: [u8; nucypher_core::Address::SIZE],
         encrypted_kfrag: &EncryptedKeyFrag,
+        issued_at: Option<u32>,
     ) -> Self {
         let address = nucypher_core::Address::new(&staking_provider_address);
         Self {
-            backend: nucypher_core::RevocationOrder::new(
+            backend: nucypher_core::RevocationOrder::new_with_issued_at(
                 &signer.backend,
                 &address,
                 &encrypted_kfrag.backend,
+                issued_at,
             ),
         }
     }
 
+    #[getter]
+    fn issued_at(&self) -> Option<u32> {
+        self.backend.issued_at()
+    }
+
+    fn is_after(&self, epoch: u32) -> bool {
+        self.backend.is_after(epoch)
+    }
+
     pub fn verify(
         &self,
         alice_verifying_key: &PublicKey,
@@ -871,8 +914,9 @@ impl NodeMetadata {
         }
     }
 
-    pub fn verify(&self) -> bool {
-        self.backend.verify()
+    pub fn verify(&self, py: Python) -> bool {
+        let backend = &self.backend;
+        py.allow_threads(|| backend.verify())
     }
 
     #[getter]
@@ -967,17 +1011,15 @@ impl MetadataRequest {
     pub fn new(
         fleet_state_checksum: &FleetStateChecksum,
         announce_nodes: Vec<NodeMetadata>,
-    ) -> Self {
+    ) -> PyResult<Self> {
         let nodes_backend = announce_nodes
             .iter()
             .map(|node| node.backend.clone())
             .collect::<Vec<_>>();
-        Self {
-            backend: nucypher_core::MetadataRequest::new(
-                &fleet_state_checksum.backend,
-                &nodes_backend,
-            ),
-        }
+        let backend =
+            nucypher_core::MetadataRequest::try_new(&fleet_state_checksum.backend, &nodes_backend)
+                .map_err(|err| PyValueError::new_err(format!("{}", err)))?;
+        Ok(Self { backend })
     }
 
     #[getter]
@@ -1020,14 +1062,15 @@ pub struct MetadataResponsePayload {
 #[pymethods]
 impl MetadataResponsePayload {
     #[new]
-    fn new(timestamp_epoch: u32, announce_nodes: Vec<NodeMetadata>) -> Self {
+    fn new(timestamp_epoch: u32, announce_nodes: Vec<NodeMetadata>) -> PyResult<Self> {
         let nodes_backend = announce_nodes
             .iter()
             .map(|node| node.backend.clone())
             .collect::<Vec<_>>();
-        MetadataResponsePayload {
-            backend: nucypher_core::MetadataResponsePayload::new(timestamp_epoch, &nodes_backend),
-        }
+        let backend =
+            nucypher_core::MetadataResponsePayload::try_new(timestamp_epoch, &nodes_backend)
+                .map_err(|err| PyValueError::new_err(format!("{}", err)))?;
+        Ok(MetadataResponsePayload { backend })
     }
 
     #[getter]
@@ -1077,10 +1120,15 @@ impl MetadataResponse {
         }
     }
 
-    pub fn verify(&self, verifying_pk: &PublicKey) -> PyResult<MetadataResponsePayload> {
-        self.backend
-            .clone()
-            .verify(&verifying_pk.backend)
+    pub fn verify(
+        &self,
+        py: Python,
+        verifying_pk: &PublicKey,
+    ) -> PyResult<MetadataResponsePayload> {
+        let backend = self.backend.clone();
+        let verifying_pk = verifying_pk.backend;
+        // The response may contain many nodes, so release the GIL while verifying the signature.
+        py.allow_threads(move || backend.verify(&verifying_pk))
             .map(|backend_payload| MetadataResponsePayload {
                 backend: backend_payload,
             })
@@ -0,0 +1,58 @@
+import sys
+import threading
+
+from nucypher_core import NodeMetadata, NodeMetadataPayload
+from nucypher_core.umbral import SecretKey, Signer
+
+
+VERIFICATIONS = 64
+
+
+def make_node_metadata():
+    signing_key = SecretKey.random()
+    payload = NodeMetadataPayload(
+        staking_provider_address=b"00000000000000000001",
+        domain="localhost",
+        timestamp_epoch=1546300800,
+        verifying_key=signing_key.public_key(),
+        encrypting_key=SecretKey.random().public_key(),
+        # A large certificate makes the Rust-side part of `verify()`
+        # (serializing and hashing the payload) take long enough for another thread to run.
+        certificate_der=b"\x00" * 1_000_000,
+        host="https://localhost.com",
+        port=443,
+        operator_signature=None,
+    )
+    return NodeMetadata(Signer(signing_key), payload)
+
+
+def test_verify_releases_gil():
+    node = make_node_metadata()
+
+    go = threading.Event()
+    worker_ran = []
+
+    def worker():
+        go.wait()
+        worker_ran.append(True)
+
+    thread = threading.Thread(target=worker)
+    thread.start()
+
+    # Stop the interpreter from switching threads on its own,
+    # so that the only way for the worker to run before this thread waits for it
+    # is `verify()` releasing the GIL.
+    switch_interval = sys.getswitchinterval()
+    sys.setswitchinterval(1000)
+    try:
+        go.set()
+        for _ in range(VERIFICATIONS):
+            assert node.verify()
+            if worker_ran:
+                break
+        ran_during_verify = bool(worker_ran)
+    finally:
+        sys.setswitchinterval(switch_interval)
+        thread.join()
+
+    assert ran_during_verify
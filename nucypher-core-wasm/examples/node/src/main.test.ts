@@ -108,7 +108,7 @@ const makefleetStateChecksum = () => {
     makeNodeMetadata(sk),
     makeNodeMetadata(sk),
   ];
-  const builder = new FleetStateChecksumBuilder(thisNode);
+  const builder = new FleetStateChecksumBuilder().setThisNode(thisNode);
   for (const node of otherNodes) {
     builder.addOtherNode(node);
   }
@@ -16,7 +16,7 @@ use alloc::{
     vec::Vec,
 };
 use core::fmt;
-use js_sys::Error;
+use js_sys::{Error, Map, Object, Reflect, Uint8Array};
 use nucypher_core::k256::ecdsa::recoverable;
 use nucypher_core::k256::ecdsa::signature::Signature as SignatureTrait;
 use nucypher_core::ProtocolObject;
@@ -24,6 +24,7 @@ use serde::{Deserialize, Serialize};
 use umbral_pre::bindings_wasm::{
     Capsule, PublicKey, SecretKey, Signer, VerifiedCapsuleFrag, VerifiedKeyFrag,
 };
+use umbral_pre::SerializableToArray;
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
 
 fn map_js_err<T: fmt::Display>(err: T) -> JsValue {
@@ -46,6 +47,17 @@ where
     obj.as_backend().to_bytes()
 }
 
+fn to_bytes_into<'a, T, U>(obj: &T, buffer: &mut SerializationBuffer) -> usize
+where
+    T: AsBackend<U>,
+    U: ProtocolObject<'a>,
+{
+    // Serialize straight into the retained memory instead of allocating a new array.
+    buffer.0.clear();
+    obj.as_backend().to_bytes_into(&mut buffer.0);
+    buffer.0.len()
+}
+
 fn from_bytes<'a, T, U>(data: &'a [u8]) -> Result<T, JsValue>
 where
     T: FromBackend<U>,
@@ -55,16 +67,55 @@ where
 }
 
 fn try_make_address(address_bytes: &[u8]) -> Result<nucypher_core::Address, JsValue> {
-    address_bytes
-        .try_into()
-        .map(nucypher_core::Address::new)
-        .map_err(|_err| {
-            JsValue::from(Error::new(&format!(
-                "Incorrect address size: {}, expected {}",
-                address_bytes.len(),
-                nucypher_core::Address::SIZE
-            )))
-        })
+    nucypher_core::Address::from_slice(address_bytes).map_err(|_err| {
+        // Addresses often come from user data, so name the offending one
+        // to make it easier to find among several.
+        let address_hex: String = address_bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        JsValue::from(Error::new(&format!(
+            "Incorrect address size: {}, expected {} (address: 0x{})",
+            address_bytes.len(),
+            nucypher_core::Address::SIZE,
+            address_hex
+        )))
+    })
+}
+
+//
+// SerializationBuffer
+//
+
+#[wasm_bindgen]
+pub struct SerializationBuffer(Vec<u8>);
+
+#[wasm_bindgen]
+impl SerializationBuffer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
+
+    #[wasm_bindgen(method, getter)]
+    pub fn length(&self) -> usize {
+        self.0.len()
+    }
+
+    #[wasm_bindgen(js_name = copyInto)]
+    pub fn copy_into(&self, target: &Uint8Array) -> Result<usize, JsValue> {
+        let len = self.0.len();
+        if (target.length() as usize) < len {
+            return Err(Error::new(&format!(
+                "Target array is too small: {} bytes, need {}",
+                target.length(),
+                len
+            ))
+            .into());
+        }
+        target.subarray(0, len as u32).copy_from(&self.0);
+        Ok(len)
+    }
 }
 
 //
@@ -97,6 +148,16 @@ impl MessageKit {
         ))
     }
 
+    #[wasm_bindgen(js_name = newNonEmpty)]
+    pub fn new_non_empty(
+        policy_encrypting_key: &PublicKey,
+        plaintext: &[u8],
+    ) -> Result<MessageKit, JsValue> {
+        nucypher_core::MessageKit::new_non_empty(policy_encrypting_key.inner(), plaintext)
+            .map(MessageKit)
+            .map_err(map_js_err)
+    }
+
     #[wasm_bindgen(js_name = withCFrag)]
     pub fn with_cfrag(&self, cfrag: &VerifiedCapsuleFrag) -> MessageKitWithFrags {
         MessageKitWithFrags {
@@ -109,6 +170,19 @@ impl MessageKit {
         self.0.decrypt(sk.inner()).map_err(map_js_err)
     }
 
+    #[wasm_bindgen(js_name = fromRetrievalKit)]
+    pub fn from_retrieval_kit(retrieval_kit: &RetrievalKit, ciphertext: &[u8]) -> MessageKit {
+        MessageKit(nucypher_core::MessageKit::from_retrieval_kit(
+            &retrieval_kit.0,
+            ciphertext,
+        ))
+    }
+
+    #[wasm_bindgen(method, getter)]
+    pub fn ciphertext(&self) -> Box<[u8]> {
+        self.0.ciphertext().into()
+    }
+
     #[wasm_bindgen(method, getter)]
     pub fn capsule(&self) -> Capsule {
         Capsule::new(self.0.capsule)
@@ -123,6 +197,20 @@ impl MessageKit {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> MessageKit {
+        Self(self.0.clone())
+    }
+
+    pub fn equals(&self, other: &MessageKit) -> bool {
+        self.0 == other.0
+    }
 }
 
 #[wasm_bindgen]
@@ -155,6 +243,36 @@ impl MessageKitWithFrags {
             )
             .map_err(map_js_err)
     }
+
+    #[wasm_bindgen(js_name = decryptReencryptedWithThreshold)]
+    pub fn decrypt_reencrypted_with_threshold(
+        &self,
+        sk: &SecretKey,
+        policy_encrypting_key: &PublicKey,
+        threshold: u8,
+    ) -> Result<Box<[u8]>, JsValue> {
+        self.message_kit
+            .0
+            .decrypt_reencrypted_with_threshold(
+                sk.inner(),
+                policy_encrypting_key.inner(),
+                self.cfrags.clone(),
+                threshold,
+            )
+            .map_err(map_decrypt_reencrypted_err)
+    }
+}
+
+// Attaches the cfrag counts to the JS error, so that the caller can inspect them.
+fn map_decrypt_reencrypted_err(err: nucypher_core::DecryptReencryptedError) -> JsValue {
+    let js_err = Error::new(&format!("{}", err));
+    if let nucypher_core::DecryptReencryptedError::InsufficientCapsuleFrags { got, needed } = err {
+        js_err.set_name("InsufficientCapsuleFrags");
+        // Setting a property on a fresh `Error` object cannot fail.
+        Reflect::set(&js_err, &"got".into(), &JsValue::from(got as u32)).unwrap();
+        Reflect::set(&js_err, &"needed".into(), &JsValue::from(needed as u32)).unwrap();
+    }
+    js_err.into()
 }
 
 //
@@ -194,14 +312,20 @@ impl HRAC {
 
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(bytes: &[u8]) -> Result<HRAC, JsValue> {
-        let bytes: [u8; nucypher_core::HRAC::SIZE] = bytes.try_into().map_err(map_js_err)?;
-        Ok(Self(bytes.into()))
+        nucypher_core::HRAC::from_slice(bytes)
+            .map(Self)
+            .map_err(map_js_err)
     }
 
     #[wasm_bindgen(js_name = toBytes)]
     pub fn to_bytes(&self) -> Box<[u8]> {
         self.0.as_ref().to_vec().into_boxed_slice()
     }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> HRAC {
+        Self(self.0)
+    }
 }
 
 //
@@ -253,6 +377,11 @@ impl EncryptedKeyFrag {
             .map(VerifiedKeyFrag::new)
     }
 
+    #[wasm_bindgen(js_name = serializedLen)]
+    pub fn serialized_len(&self) -> usize {
+        self.0.serialized_len()
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<EncryptedKeyFrag, JsValue> {
         from_bytes(data)
@@ -262,6 +391,20 @@ impl EncryptedKeyFrag {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> EncryptedKeyFrag {
+        Self(self.0.clone())
+    }
+
+    pub fn equals(&self, other: &EncryptedKeyFrag) -> bool {
+        self.0 == other.0
+    }
 }
 
 //
@@ -283,9 +426,16 @@ impl TreasureMapBuilder {
             policy_encrypting_key: *policy_encrypting_key.inner(),
             assigned_kfrags: Vec::new(),
             threshold,
+            expiration_epoch: None,
         })
     }
 
+    #[wasm_bindgen(js_name = setExpirationEpoch)]
+    pub fn set_expiration_epoch(&mut self, expiration_epoch: u32) -> TreasureMapBuilder {
+        self.expiration_epoch = Some(expiration_epoch);
+        self.clone()
+    }
+
     #[wasm_bindgen(js_name = addKfrag)]
     pub fn add_kfrag(
         &mut self,
@@ -301,12 +451,13 @@ impl TreasureMapBuilder {
 
     #[wasm_bindgen]
     pub fn build(&self) -> TreasureMap {
-        TreasureMap(nucypher_core::TreasureMap::new(
+        TreasureMap(nucypher_core::TreasureMap::new_with_expiration(
             &self.signer,
             &self.hrac,
             &self.policy_encrypting_key,
             self.assigned_kfrags.clone(),
             self.threshold,
+            self.expiration_epoch,
         ))
     }
 }
@@ -338,6 +489,7 @@ pub struct TreasureMapBuilder {
         (umbral_pre::PublicKey, umbral_pre::VerifiedKeyFrag),
     )>,
     threshold: u8,
+    expiration_epoch: Option<u32>,
 }
 
 #[wasm_bindgen]
@@ -355,6 +507,49 @@ impl TreasureMap {
         Ok(serde_wasm_bindgen::to_value(&result)?)
     }
 
+    #[wasm_bindgen]
+    pub fn validate(&self) -> Result<(), JsValue> {
+        self.0.validate().map_err(map_js_err)
+    }
+
+    #[wasm_bindgen(method, getter, js_name = destinationCount)]
+    pub fn destination_count(&self) -> usize {
+        self.0.destination_count()
+    }
+
+    #[wasm_bindgen(js_name = encryptedKfragFor)]
+    pub fn encrypted_kfrag_for(&self, address: &[u8]) -> Result<Option<EncryptedKeyFrag>, JsValue> {
+        let address = try_make_address(address)?;
+        Ok(self
+            .0
+            .encrypted_kfrag_for(&address)
+            .cloned()
+            .map(EncryptedKeyFrag))
+    }
+
+    #[wasm_bindgen(js_name = destinationsChainOrdered)]
+    pub fn destinations_chain_ordered(&self) -> Result<JsValue, JsValue> {
+        let result = self
+            .0
+            .destinations_chain_ordered()
+            .into_iter()
+            .map(|(address, ekfrag)| (address, EncryptedKeyFrag(ekfrag.clone())))
+            .collect::<Vec<_>>();
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
+    #[wasm_bindgen(js_name = destinationsMap)]
+    pub fn destinations_map(&self) -> Map {
+        let result = Map::new();
+        for (address, ekfrag) in &self.0.destinations {
+            result.set(
+                &JsValue::from(address.to_checksum_address()),
+                &JsValue::from(EncryptedKeyFrag(ekfrag.clone())),
+            );
+        }
+        result
+    }
+
     #[wasm_bindgen(js_name = makeRevocationOrders)]
     pub fn make_revocation_orders(&self, signer: &Signer) -> Vec<JsValue> {
         self.0
@@ -374,6 +569,41 @@ impl TreasureMap {
         self.0.threshold
     }
 
+    #[wasm_bindgen(method, getter, js_name = expirationEpoch)]
+    pub fn expiration_epoch(&self) -> Option<u32> {
+        self.0.expiration_epoch
+    }
+
+    #[wasm_bindgen(js_name = isExpired)]
+    pub fn is_expired(&self, now_epoch: u32) -> bool {
+        self.0.is_expired(now_epoch)
+    }
+
+    #[wasm_bindgen(js_name = responsesStillNeeded)]
+    pub fn responses_still_needed(&self, verified_so_far: usize) -> usize {
+        self.0.responses_still_needed(verified_so_far)
+    }
+
+    #[wasm_bindgen(js_name = recommendedContactCount)]
+    pub fn recommended_contact_count(&self, expected_failure_rate: f32) -> usize {
+        self.0.recommended_contact_count(expected_failure_rate)
+    }
+
+    #[wasm_bindgen(js_name = serializedLen)]
+    pub fn serialized_len(&self) -> usize {
+        self.0.serialized_len()
+    }
+
+    #[wasm_bindgen(js_name = samePolicyAs)]
+    pub fn same_policy_as(&self, other: &TreasureMap) -> bool {
+        self.0.same_policy_as(&other.0)
+    }
+
+    #[wasm_bindgen(js_name = policyFingerprint)]
+    pub fn policy_fingerprint(&self) -> Box<[u8]> {
+        Box::new(self.0.policy_fingerprint())
+    }
+
     #[wasm_bindgen(method, getter, js_name = policyEncryptingKey)]
     pub fn policy_encrypting_key(&self) -> PublicKey {
         PublicKey::new(self.0.policy_encrypting_key)
@@ -393,6 +623,135 @@ impl TreasureMap {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> TreasureMap {
+        Self(self.0.clone())
+    }
+
+    pub fn equals(&self, other: &TreasureMap) -> bool {
+        self.0 == other.0
+    }
+}
+
+//
+// TreasureMapArchive
+//
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct TreasureMapArchiveBuilder {
+    treasure_maps: Vec<nucypher_core::TreasureMap>,
+}
+
+#[wasm_bindgen]
+impl TreasureMapArchiveBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = addTreasureMap)]
+    pub fn add_treasure_map(&mut self, treasure_map: &TreasureMap) -> TreasureMapArchiveBuilder {
+        self.treasure_maps.push(treasure_map.0.clone());
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn build(&self) -> TreasureMapArchive {
+        TreasureMapArchive(nucypher_core::TreasureMapArchive::new(&self.treasure_maps))
+    }
+}
+
+#[wasm_bindgen]
+#[derive(PartialEq, Debug)]
+pub struct TreasureMapArchive(nucypher_core::TreasureMapArchive);
+
+impl AsBackend<nucypher_core::TreasureMapArchive> for TreasureMapArchive {
+    fn as_backend(&self) -> &nucypher_core::TreasureMapArchive {
+        &self.0
+    }
+}
+
+impl FromBackend<nucypher_core::TreasureMapArchive> for TreasureMapArchive {
+    fn from_backend(backend: nucypher_core::TreasureMapArchive) -> Self {
+        TreasureMapArchive(backend)
+    }
+}
+
+#[wasm_bindgen]
+impl TreasureMapArchive {
+    #[wasm_bindgen(method, getter, js_name = treasureMaps)]
+    pub fn treasure_maps(&self) -> Vec<JsValue> {
+        self.0
+            .treasure_maps()
+            .iter()
+            .cloned()
+            .map(TreasureMap)
+            .map(JsValue::from)
+            .collect()
+    }
+
+    pub fn get(&self, hrac: &HRAC) -> Option<TreasureMap> {
+        self.0.get(&hrac.0).cloned().map(TreasureMap)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(data: &[u8]) -> Result<TreasureMapArchive, JsValue> {
+        from_bytes(data)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        to_bytes(self)
+    }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+}
+
+//
+// TreasureMapStore
+//
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct TreasureMapStore(nucypher_core::TreasureMapStore);
+
+#[wasm_bindgen]
+impl TreasureMapStore {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, treasure_map: &TreasureMap) -> Option<TreasureMap> {
+        self.0.insert(treasure_map.0.clone()).map(TreasureMap)
+    }
+
+    pub fn get(&self, hrac: &HRAC) -> Option<TreasureMap> {
+        self.0.get(&hrac.0).cloned().map(TreasureMap)
+    }
+
+    pub fn remove(&mut self, hrac: &HRAC) -> Option<TreasureMap> {
+        self.0.remove(&hrac.0).map(TreasureMap)
+    }
+
+    pub fn contains(&self, hrac: &HRAC) -> bool {
+        self.0.contains(&hrac.0)
+    }
+
+    #[wasm_bindgen(method, getter)]
+    pub fn size(&self) -> usize {
+        self.0.len()
+    }
 }
 
 //
@@ -428,6 +787,28 @@ impl EncryptedTreasureMap {
             .map(TreasureMap)
     }
 
+    #[wasm_bindgen(js_name = decryptAvailable)]
+    pub fn decrypt_available(
+        &self,
+        sk: &SecretKey,
+        publisher_verifying_key: &PublicKey,
+        reachable: Vec<JsValue>,
+    ) -> Result<JsValue, JsValue> {
+        let reachable = reachable
+            .iter()
+            .map(|address| try_make_address(&Uint8Array::new(address).to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let available = self
+            .0
+            .decrypt_available(sk.inner(), publisher_verifying_key.inner(), &reachable)
+            .map_err(map_js_err)?;
+        let result: Vec<_> = available
+            .into_iter()
+            .map(|(address, ekfrag)| (address, EncryptedKeyFrag(ekfrag)))
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<EncryptedTreasureMap, JsValue> {
         from_bytes(data)
@@ -437,6 +818,20 @@ impl EncryptedTreasureMap {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> EncryptedTreasureMap {
+        Self(self.0.clone())
+    }
+
+    pub fn equals(&self, other: &EncryptedTreasureMap) -> bool {
+        self.0 == other.0
+    }
 }
 
 //
@@ -527,6 +922,10 @@ impl ReencryptionRequest {
         EncryptedKeyFrag(self.0.encrypted_kfrag.clone())
     }
 
+    pub fn precheck(&self, ursula_sk: &SecretKey) -> Result<(), JsValue> {
+        self.0.precheck(ursula_sk.inner()).map_err(map_js_err)
+    }
+
     #[wasm_bindgen(method, getter)]
     pub fn capsules(&self) -> Vec<JsValue> {
         self.0
@@ -537,6 +936,20 @@ impl ReencryptionRequest {
             .collect()
     }
 
+    #[wasm_bindgen(method, getter, js_name = capsuleCount)]
+    pub fn capsule_count(&self) -> usize {
+        self.0.capsules.len()
+    }
+
+    #[wasm_bindgen(js_name = capsuleAt)]
+    pub fn capsule_at(&self, index: usize) -> Result<Capsule, JsValue> {
+        self.0
+            .capsules
+            .get(index)
+            .map(|capsule| Capsule::new(*capsule))
+            .ok_or_else(|| JsValue::from(Error::new("Capsule index out of range")))
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<ReencryptionRequest, JsValue> {
         from_bytes(data)
@@ -546,6 +959,11 @@ impl ReencryptionRequest {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
 }
 
 //
@@ -591,6 +1009,24 @@ impl ReencryptionResponseBuilder {
             self.vcfrags.clone(),
         ))
     }
+
+    #[wasm_bindgen(js_name = buildFromCfragBytes)]
+    pub fn build_from_cfrag_bytes(
+        &self,
+        cfrags: Vec<JsValue>,
+    ) -> Result<ReencryptionResponse, JsValue> {
+        let cfrag_bytes: Vec<Vec<u8>> = cfrags
+            .iter()
+            .map(|cfrag| Uint8Array::new(cfrag).to_vec())
+            .collect();
+        nucypher_core::ReencryptionResponse::from_cfrag_bytes(
+            &self.signer,
+            &self.capsules,
+            &cfrag_bytes,
+        )
+        .map(ReencryptionResponse)
+        .map_err(map_js_err)
+    }
 }
 
 #[wasm_bindgen]
@@ -618,6 +1054,16 @@ impl ReencryptionResponse {
         }
     }
 
+    #[wasm_bindgen(js_name = unverifiedCfrags)]
+    pub fn unverified_cfrags(&self) -> Box<[JsValue]> {
+        self.0
+            .clone()
+            .into_unverified_cfrags()
+            .iter()
+            .map(|cfrag_bytes| Uint8Array::from(cfrag_bytes.as_ref()).into())
+            .collect()
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<ReencryptionResponse, JsValue> {
         from_bytes(data)
@@ -627,6 +1073,16 @@ impl ReencryptionResponse {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> ReencryptionResponse {
+        Self(self.0.clone())
+    }
 }
 
 impl ReencryptionResponse {
@@ -683,17 +1139,185 @@ impl ReencryptionResponseWithCapsules {
             .map_err(map_js_err)?;
         Ok(vcfrags_backend_js)
     }
+
+    #[wasm_bindgen(js_name = filterValid)]
+    pub fn filter_valid(
+        &self,
+        signer: &Signer,
+        alice_verifying_key: &PublicKey,
+        ursula_verifying_key: &PublicKey,
+        policy_encrypting_key: &PublicKey,
+        bob_encrypting_key: &PublicKey,
+    ) -> Result<ReencryptionResponseWithCapsules, JsValue> {
+        let (capsules, reencryption_response) = self
+            .reencryption_response
+            .filter_valid(
+                signer.inner(),
+                &self.capsules,
+                alice_verifying_key.inner(),
+                ursula_verifying_key.inner(),
+                policy_encrypting_key.inner(),
+                bob_encrypting_key.inner(),
+            )
+            .map_err(|_err| {
+                JsValue::from(Error::new("ReencryptionResponse verification failed"))
+            })?;
+        Ok(ReencryptionResponseWithCapsules {
+            reencryption_response,
+            capsules: capsules.into_vec(),
+        })
+    }
+
+    #[wasm_bindgen(method, getter, js_name = reencryptionResponse)]
+    pub fn reencryption_response(&self) -> ReencryptionResponse {
+        ReencryptionResponse(self.reencryption_response.clone())
+    }
+
+    #[wasm_bindgen(method, getter)]
+    pub fn capsules(&self) -> Vec<JsValue> {
+        self.capsules
+            .iter()
+            .map(|capsule| Capsule::new(*capsule))
+            .map(JsValue::from)
+            .collect()
+    }
 }
 
 //
-// RetrievalKit
+// CfragCollector
 //
 
 #[wasm_bindgen]
 #[derive(Clone)]
-pub struct RetrievalKitBuilder {
-    capsule: umbral_pre::Capsule,
-    queried_addresses: Vec<nucypher_core::Address>,
+pub struct CfragCollectorBuilder {
+    capsules: Vec<umbral_pre::Capsule>,
+    alice_verifying_key: umbral_pre::PublicKey,
+    policy_encrypting_key: umbral_pre::PublicKey,
+    bob_encrypting_key: umbral_pre::PublicKey,
+    threshold: u8,
+}
+
+#[wasm_bindgen]
+impl CfragCollectorBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        alice_verifying_key: &PublicKey,
+        policy_encrypting_key: &PublicKey,
+        bob_encrypting_key: &PublicKey,
+        threshold: u8,
+    ) -> Self {
+        Self {
+            capsules: Vec::new(),
+            alice_verifying_key: *alice_verifying_key.inner(),
+            policy_encrypting_key: *policy_encrypting_key.inner(),
+            bob_encrypting_key: *bob_encrypting_key.inner(),
+            threshold,
+        }
+    }
+
+    #[wasm_bindgen(js_name = addCapsule)]
+    pub fn add_capsule(&mut self, capsule: &Capsule) -> CfragCollectorBuilder {
+        self.capsules.push(*capsule.inner());
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn build(&self) -> CfragCollector {
+        CfragCollector(nucypher_core::CfragCollector::new(
+            &self.capsules,
+            &self.alice_verifying_key,
+            &self.policy_encrypting_key,
+            &self.bob_encrypting_key,
+            self.threshold,
+        ))
+    }
+}
+
+#[wasm_bindgen]
+pub struct CfragCollector(nucypher_core::CfragCollector);
+
+#[wasm_bindgen]
+impl CfragCollector {
+    pub fn add(
+        &mut self,
+        response: &ReencryptionResponse,
+        ursula_verifying_key: &PublicKey,
+    ) -> Result<(), JsValue> {
+        self.0
+            .add(&response.0, ursula_verifying_key.inner())
+            .map_err(|_err| JsValue::from(Error::new("ReencryptionResponse verification failed")))
+    }
+
+    #[wasm_bindgen(js_name = isComplete)]
+    pub fn is_complete(&self) -> bool {
+        self.0.is_complete()
+    }
+
+    #[wasm_bindgen(method, getter, js_name = responsesCount)]
+    pub fn responses_count(&self) -> usize {
+        self.0.responses_count()
+    }
+
+    pub fn cfrags(&self, capsule_index: usize) -> Result<Box<[JsValue]>, JsValue> {
+        let vcfrags = self
+            .0
+            .cfrags(capsule_index)
+            .ok_or_else(|| JsValue::from(Error::new("Capsule index out of range")))?;
+        vcfrags
+            .iter()
+            .map(|vcfrag| JsValue::from_serde(&VerifiedCapsuleFrag::new(vcfrag.clone())))
+            .collect::<Result<Box<_>, _>>()
+            .map_err(map_js_err)
+    }
+}
+
+//
+// ResponseAggregator
+//
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct ResponseAggregator(nucypher_core::ResponseAggregator);
+
+#[wasm_bindgen]
+impl ResponseAggregator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self(nucypher_core::ResponseAggregator::new())
+    }
+
+    pub fn add(
+        &mut self,
+        ursula_verifying_key: &PublicKey,
+        response: &ReencryptionResponse,
+    ) -> bool {
+        self.0.add(ursula_verifying_key.inner(), &response.0)
+    }
+
+    #[wasm_bindgen(js_name = uniqueCount)]
+    pub fn unique_count(&self) -> usize {
+        self.0.unique_count()
+    }
+
+    pub fn responses(&self) -> Vec<JsValue> {
+        self.0
+            .responses()
+            .iter()
+            .map(|(_verifying_key, response)| ReencryptionResponse(response.clone()))
+            .map(JsValue::from)
+            .collect()
+    }
+}
+
+//
+// RetrievalKit
+//
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct RetrievalKitBuilder {
+    capsule: umbral_pre::Capsule,
+    queried_addresses: Vec<nucypher_core::Address>,
 }
 
 #[wasm_bindgen]
@@ -762,6 +1386,16 @@ impl RetrievalKit {
             .map_err(map_js_err)
     }
 
+    /// Returns the queried addresses as `0x`-prefixed checksum addresses.
+    #[wasm_bindgen(js_name = queriedAddressesHex)]
+    pub fn queried_addresses_hex(&self) -> Vec<JsValue> {
+        self.0
+            .queried_addresses
+            .iter()
+            .map(|address| JsValue::from(address.to_checksum_address()))
+            .collect()
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<RetrievalKit, JsValue> {
         from_bytes(data)
@@ -771,6 +1405,90 @@ impl RetrievalKit {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+}
+
+//
+// MultiRetrievalKit
+//
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct MultiRetrievalKitBuilder {
+    capsules: Vec<umbral_pre::Capsule>,
+    queried_addresses: Vec<nucypher_core::Address>,
+}
+
+#[wasm_bindgen]
+impl MultiRetrievalKitBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = addMessageKit)]
+    pub fn add_message_kit(&mut self, message_kit: &MessageKit) -> MultiRetrievalKitBuilder {
+        self.capsules.push(message_kit.0.capsule);
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = addQueriedAddress)]
+    pub fn add_queried_address(
+        &mut self,
+        address: &[u8],
+    ) -> Result<MultiRetrievalKitBuilder, JsValue> {
+        let address = try_make_address(address)?;
+        self.queried_addresses.push(address);
+        Ok(self.clone())
+    }
+
+    #[wasm_bindgen]
+    pub fn build(&self) -> MultiRetrievalKit {
+        MultiRetrievalKit(nucypher_core::MultiRetrievalKit::new(
+            &self.capsules,
+            self.queried_addresses.clone(),
+        ))
+    }
+}
+
+#[wasm_bindgen]
+pub struct MultiRetrievalKit(nucypher_core::MultiRetrievalKit);
+
+#[wasm_bindgen]
+impl MultiRetrievalKit {
+    #[wasm_bindgen(method, getter)]
+    pub fn capsules(&self) -> Vec<JsValue> {
+        self.0
+            .capsules
+            .iter()
+            .map(|capsule| Capsule::new(*capsule))
+            .map(JsValue::from)
+            .collect()
+    }
+
+    #[wasm_bindgen(method, getter, js_name = queriedAddresses)]
+    pub fn queried_addresses(&self) -> Result<Vec<JsValue>, JsValue> {
+        self.0
+            .queried_addresses
+            .iter()
+            .map(|address| JsValue::from_serde(&address))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(map_js_err)
+    }
+
+    #[wasm_bindgen(js_name = retrievalKits)]
+    pub fn retrieval_kits(&self) -> Vec<JsValue> {
+        self.0
+            .retrieval_kits()
+            .into_iter()
+            .map(RetrievalKit)
+            .map(JsValue::from)
+            .collect()
+    }
 }
 
 //
@@ -809,6 +1527,42 @@ impl RevocationOrder {
         )))
     }
 
+    #[wasm_bindgen(js_name = newWithIssuedAt)]
+    pub fn new_with_issued_at(
+        signer: &Signer,
+        staking_provider_address: &[u8],
+        encrypted_kfrag: &EncryptedKeyFrag,
+        issued_at: u32,
+    ) -> Result<RevocationOrder, JsValue> {
+        let address = try_make_address(staking_provider_address)?;
+        Ok(Self(nucypher_core::RevocationOrder::new_with_issued_at(
+            signer.inner(),
+            &address,
+            &encrypted_kfrag.0,
+            Some(issued_at),
+        )))
+    }
+
+    #[wasm_bindgen(method, getter, js_name = ursulaAddress)]
+    pub fn ursula_address(&self) -> Vec<u8> {
+        self.0.staking_provider_address().as_ref().to_vec()
+    }
+
+    #[wasm_bindgen(method, getter, js_name = encryptedKfrag)]
+    pub fn encrypted_kfrag(&self) -> EncryptedKeyFrag {
+        EncryptedKeyFrag(self.0.encrypted_kfrag().clone())
+    }
+
+    #[wasm_bindgen(method, getter, js_name = issuedAt)]
+    pub fn issued_at(&self) -> Option<u32> {
+        self.0.issued_at()
+    }
+
+    #[wasm_bindgen(js_name = isAfter)]
+    pub fn is_after(&self, epoch: u32) -> bool {
+        self.0.is_after(epoch)
+    }
+
     #[wasm_bindgen]
     pub fn verify(
         &self,
@@ -824,6 +1578,17 @@ impl RevocationOrder {
             .map_err(|_err| Error::new("Failed to verify RevocationOrder").into())
     }
 
+    #[wasm_bindgen(js_name = toChainTuple)]
+    pub fn to_chain_tuple(&self) -> Result<RevocationOrderChainTuple, JsValue> {
+        let (address, encrypted_kfrag_hash, signature) =
+            self.0.to_chain_tuple().map_err(map_js_err)?;
+        Ok(RevocationOrderChainTuple {
+            address: address.as_ref().to_vec().into_boxed_slice(),
+            encrypted_kfrag_hash: encrypted_kfrag_hash.to_vec().into_boxed_slice(),
+            signature: signature.into_boxed_slice(),
+        })
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<RevocationOrder, JsValue> {
         from_bytes(data)
@@ -833,6 +1598,16 @@ impl RevocationOrder {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> RevocationOrder {
+        Self(self.0.clone())
+    }
 }
 
 // wasm-bindgen does not support returning tuples, so have to use a struct.
@@ -855,13 +1630,139 @@ impl VerifiedRevocationOrder {
     }
 }
 
+// wasm-bindgen does not support returning tuples, so have to use a struct.
+#[wasm_bindgen]
+pub struct RevocationOrderChainTuple {
+    address: Box<[u8]>,
+    encrypted_kfrag_hash: Box<[u8]>,
+    signature: Box<[u8]>,
+}
+
+#[wasm_bindgen]
+impl RevocationOrderChainTuple {
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> Box<[u8]> {
+        self.address.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name=encryptedKFragHash)]
+    pub fn encrypted_kfrag_hash(&self) -> Box<[u8]> {
+        self.encrypted_kfrag_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn signature(&self) -> Box<[u8]> {
+        self.signature.clone()
+    }
+}
+
 //
 // NodeMetadataPayload
 //
 
+fn try_make_operator_signature(signature_bytes: &[u8]) -> Result<recoverable::Signature, JsValue> {
+    recoverable::Signature::from_bytes(signature_bytes).map_err(|err| {
+        JsValue::from(Error::new(&format!(
+            "Incorrect operator signature format: {}",
+            err
+        )))
+    })
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct NodeMetadataPayloadBuilder(nucypher_core::NodeMetadataPayloadBuilder);
+
+#[wasm_bindgen]
+impl NodeMetadataPayloadBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = stakingProviderAddress)]
+    pub fn staking_provider_address(
+        &mut self,
+        address: &[u8],
+    ) -> Result<NodeMetadataPayloadBuilder, JsValue> {
+        let address = try_make_address(address)?;
+        self.0 = core::mem::take(&mut self.0).staking_provider_address(&address);
+        Ok(self.clone())
+    }
+
+    #[wasm_bindgen]
+    pub fn domain(&mut self, domain: &str) -> Self {
+        self.0 = core::mem::take(&mut self.0).domain(domain);
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = timestampEpoch)]
+    pub fn timestamp_epoch(&mut self, timestamp_epoch: u32) -> Self {
+        self.0 = core::mem::take(&mut self.0).timestamp_epoch(timestamp_epoch);
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = verifyingKey)]
+    pub fn verifying_key(&mut self, verifying_key: &PublicKey) -> Self {
+        self.0 = core::mem::take(&mut self.0).verifying_key(verifying_key.inner());
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = encryptingKey)]
+    pub fn encrypting_key(&mut self, encrypting_key: &PublicKey) -> Self {
+        self.0 = core::mem::take(&mut self.0).encrypting_key(encrypting_key.inner());
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = certificateDer)]
+    pub fn certificate_der(&mut self, certificate_der: &[u8]) -> Self {
+        self.0 = core::mem::take(&mut self.0).certificate_der(certificate_der);
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn host(&mut self, host: &str) -> Self {
+        self.0 = core::mem::take(&mut self.0).host(host);
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn port(&mut self, port: u16) -> Self {
+        self.0 = core::mem::take(&mut self.0).port(port);
+        self.clone()
+    }
+
+    #[wasm_bindgen(js_name = operatorSignature)]
+    pub fn operator_signature(
+        &mut self,
+        operator_signature: &[u8],
+    ) -> Result<NodeMetadataPayloadBuilder, JsValue> {
+        let signature = try_make_operator_signature(operator_signature)?;
+        self.0 = core::mem::take(&mut self.0).operator_signature(&signature);
+        Ok(self.clone())
+    }
+
+    #[wasm_bindgen]
+    pub fn build(&self) -> Result<NodeMetadataPayload, JsValue> {
+        self.0.build().map(NodeMetadataPayload).map_err(map_js_err)
+    }
+}
+
 #[wasm_bindgen]
 pub struct NodeMetadataPayload(nucypher_core::NodeMetadataPayload);
 
+impl AsBackend<nucypher_core::NodeMetadataPayload> for NodeMetadataPayload {
+    fn as_backend(&self) -> &nucypher_core::NodeMetadataPayload {
+        &self.0
+    }
+}
+
+impl FromBackend<nucypher_core::NodeMetadataPayload> for NodeMetadataPayload {
+    fn from_backend(backend: nucypher_core::NodeMetadataPayload) -> Self {
+        NodeMetadataPayload(backend)
+    }
+}
+
 #[wasm_bindgen]
 impl NodeMetadataPayload {
     #[allow(clippy::too_many_arguments)]
@@ -880,14 +1781,7 @@ impl NodeMetadataPayload {
         let address = try_make_address(staking_provider_address)?;
 
         let signature = operator_signature
-            .map(|signature_bytes| {
-                recoverable::Signature::from_bytes(&signature_bytes).map_err(|err| {
-                    JsValue::from(Error::new(&format!(
-                        "Incorrect operator signature format: {}",
-                        err
-                    )))
-                })
-            })
+            .map(|signature_bytes| try_make_operator_signature(&signature_bytes))
             .transpose()?;
 
         Ok(Self(nucypher_core::NodeMetadataPayload {
@@ -908,6 +1802,25 @@ impl NodeMetadataPayload {
         self.0.staking_provider_address.as_ref().to_vec()
     }
 
+    #[wasm_bindgen(method, getter, js_name = addressHex)]
+    pub fn address_hex(&self) -> String {
+        self.0.staking_provider_address.to_checksum_address()
+    }
+
+    #[wasm_bindgen]
+    pub fn capabilities(&self) -> JsValue {
+        let capabilities = self.0.capabilities();
+        let result = Object::new();
+        // Setting a property on a fresh object cannot fail.
+        Reflect::set(
+            &result,
+            &"operatorSignature".into(),
+            &JsValue::from(capabilities.operator_signature),
+        )
+        .unwrap();
+        result.into()
+    }
+
     #[wasm_bindgen(method, getter, js_name=verifyingKey)]
     pub fn verifying_key(&self) -> PublicKey {
         PublicKey::new(self.0.verifying_key)
@@ -950,6 +1863,22 @@ impl NodeMetadataPayload {
         self.0.certificate_der.clone()
     }
 
+    #[wasm_bindgen(method, getter, js_name = certificatePem)]
+    pub fn certificate_pem(&self) -> Result<String, JsValue> {
+        if !is_der_sequence(&self.0.certificate_der) {
+            return Err(Error::new("The certificate is not in DER format").into());
+        }
+        let encoded = base64::encode(&self.0.certificate_der);
+        let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+        // PEM requires the base64 lines to be at most 64 characters long
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(&String::from_utf8_lossy(line));
+            pem.push('\n');
+        }
+        pem.push_str("-----END CERTIFICATE-----\n");
+        Ok(pem)
+    }
+
     #[wasm_bindgen(js_name = deriveOperatorAddress)]
     pub fn derive_operator_address(&self) -> Result<Vec<u8>, JsValue> {
         self.0
@@ -957,6 +1886,59 @@ impl NodeMetadataPayload {
             .map(|address| address.as_ref().to_vec())
             .map_err(map_js_err)
     }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(data: &[u8]) -> Result<NodeMetadataPayload, JsValue> {
+        from_bytes(data)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        to_bytes(self)
+    }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> NodeMetadataPayload {
+        Self(self.0.clone())
+    }
+}
+
+// Checks that the data is a single DER-encoded SEQUENCE (which a certificate is),
+// without parsing its contents.
+fn is_der_sequence(data: &[u8]) -> bool {
+    let (tag, first_len_byte) = match data {
+        [tag, first_len_byte, ..] => (*tag, *first_len_byte),
+        _ => return false,
+    };
+    if tag != 0x30 {
+        return false;
+    }
+
+    let (header_len, content_len) = if first_len_byte < 0x80 {
+        (2, first_len_byte as usize)
+    } else {
+        // Long form: the low bits give the number of the following length bytes
+        let len_bytes = (first_len_byte & 0x7f) as usize;
+        if len_bytes == 0 || len_bytes > core::mem::size_of::<usize>() {
+            return false;
+        }
+        match data.get(2..2 + len_bytes) {
+            Some(bytes) => (
+                2 + len_bytes,
+                bytes
+                    .iter()
+                    .fold(0usize, |acc, byte| (acc << 8) | (*byte as usize)),
+            ),
+            None => return false,
+        }
+    };
+
+    header_len.checked_add(content_len) == Some(data.len())
 }
 
 //
@@ -995,6 +1977,11 @@ impl NodeMetadata {
         NodeMetadataPayload(self.0.payload.clone())
     }
 
+    #[wasm_bindgen(js_name = signedBytes)]
+    pub fn signed_bytes(&self) -> Box<[u8]> {
+        self.0.signed_bytes()
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<NodeMetadata, JsValue> {
         from_bytes(data)
@@ -1004,6 +1991,20 @@ impl NodeMetadata {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> NodeMetadata {
+        Self(self.0.clone())
+    }
+
+    pub fn equals(&self, other: &NodeMetadata) -> bool {
+        self.0 == other.0
+    }
 }
 
 // TODO: Replace inner() with From<>?
@@ -1018,24 +2019,31 @@ impl NodeMetadata {
 //
 
 #[wasm_bindgen]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct FleetStateChecksumBuilder {
     this_node: Option<nucypher_core::NodeMetadata>,
     other_nodes: Vec<nucypher_core::NodeMetadata>,
 }
 #[wasm_bindgen]
 impl FleetStateChecksumBuilder {
-    // TODO: Fix lack of reference leading to accidental freeing of memory
-    //       https://github.com/rustwasm/wasm-bindgen/issues/2370
-    // this_node: Option<&NodeMetadata>,
+    // `wasm-bindgen` does not support `Option<&T>` arguments
+    // (https://github.com/rustwasm/wasm-bindgen/issues/2370),
+    // and taking `Option<NodeMetadata>` by value frees the JS object.
+    // So the requesting node is set with a separate method taking a reference.
     #[wasm_bindgen(constructor)]
-    pub fn new(this_node: Option<NodeMetadata>) -> Self {
+    pub fn new() -> Self {
         Self {
-            this_node: this_node.map(|n| n.0),
+            this_node: None,
             other_nodes: Vec::new(),
         }
     }
 
+    #[wasm_bindgen(js_name=setThisNode)]
+    pub fn set_this_node(&mut self, this_node: &NodeMetadata) -> Self {
+        self.this_node = Some(this_node.inner().clone());
+        self.clone()
+    }
+
     #[wasm_bindgen(js_name=addOtherNode)]
     pub fn add_other_node(&mut self, other_node: &NodeMetadata) -> Self {
         self.other_nodes.push(other_node.inner().clone());
@@ -1052,7 +2060,7 @@ impl FleetStateChecksumBuilder {
 }
 
 #[wasm_bindgen]
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct FleetStateChecksum(nucypher_core::FleetStateChecksum);
 
 impl AsBackend<nucypher_core::FleetStateChecksum> for FleetStateChecksum {
@@ -1063,24 +2071,78 @@ impl AsBackend<nucypher_core::FleetStateChecksum> for FleetStateChecksum {
 
 #[wasm_bindgen]
 impl FleetStateChecksum {
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<FleetStateChecksum, JsValue> {
+        nucypher_core::FleetStateChecksum::from_slice(bytes)
+            .map(Self)
+            .map_err(map_js_err)
+    }
+
     #[wasm_bindgen(js_name = toBytes)]
     pub fn to_bytes(&self) -> Box<[u8]> {
         self.0.as_ref().to_vec().into_boxed_slice()
     }
+
+    /// Returns the checksum as a lowercase hex string (without the `0x` prefix).
+    #[wasm_bindgen(method, getter)]
+    pub fn checksum(&self) -> String {
+        self.0
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> FleetStateChecksum {
+        Self(self.0)
+    }
+
+    pub fn equals(&self, other: &FleetStateChecksum) -> bool {
+        self.0 == other.0
+    }
 }
 
 impl FleetStateChecksum {
-    pub fn new(this_node: Option<NodeMetadata>, other_nodes: Vec<NodeMetadata>) -> Self {
-        let this_node = this_node.map(|n| n.0);
+    pub fn new(this_node: Option<&NodeMetadata>, other_nodes: &[NodeMetadata]) -> Self {
         let other_nodes: Vec<nucypher_core::NodeMetadata> =
-            other_nodes.iter().cloned().map(|n| n.0).collect();
+            other_nodes.iter().map(|n| n.0.clone()).collect();
         FleetStateChecksum(nucypher_core::FleetStateChecksum::from_nodes(
-            this_node.as_ref(),
+            this_node.map(|n| &n.0),
             &other_nodes,
         ))
     }
 }
 
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct CanonicalNodesBuilder {
+    nodes: Vec<nucypher_core::NodeMetadata>,
+}
+
+#[wasm_bindgen]
+impl CanonicalNodesBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    #[wasm_bindgen(js_name=addNode)]
+    pub fn add_node(&mut self, node: &NodeMetadata) -> Self {
+        self.nodes.push(node.inner().clone());
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn build(&self) -> Vec<JsValue> {
+        nucypher_core::canonicalize_nodes(self.nodes.clone())
+            .into_iter()
+            .map(NodeMetadata)
+            .map(JsValue::from)
+            .collect()
+    }
+}
+
 //
 // MetadataRequest
 //
@@ -1109,11 +2171,10 @@ impl MetadataRequestBuilder {
     }
 
     #[wasm_bindgen]
-    pub fn build(&self) -> MetadataRequest {
-        MetadataRequest(nucypher_core::MetadataRequest::new(
-            &self.fleet_state_checksum,
-            &self.announce_nodes,
-        ))
+    pub fn build(&self) -> Result<MetadataRequest, JsValue> {
+        nucypher_core::MetadataRequest::try_new(&self.fleet_state_checksum, &self.announce_nodes)
+            .map(MetadataRequest)
+            .map_err(map_js_err)
     }
 }
 
@@ -1158,6 +2219,16 @@ impl MetadataRequest {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> MetadataRequest {
+        Self(self.0.clone())
+    }
 }
 
 //
@@ -1188,17 +2259,40 @@ impl MetadataResponsePayloadBuilder {
     }
 
     #[wasm_bindgen]
-    pub fn build(&self) -> MetadataResponsePayload {
-        MetadataResponsePayload(nucypher_core::MetadataResponsePayload::new(
-            self.timestamp_epoch,
-            &self.announce_nodes,
-        ))
+    pub fn build(&self) -> Result<MetadataResponsePayload, JsValue> {
+        nucypher_core::MetadataResponsePayload::try_new(self.timestamp_epoch, &self.announce_nodes)
+            .map(MetadataResponsePayload)
+            .map_err(map_js_err)
+    }
+
+    #[wasm_bindgen(js_name=buildCanonical)]
+    pub fn build_canonical(&self) -> Result<MetadataResponsePayload, JsValue> {
+        let mut builder = nucypher_core::MetadataResponsePayloadBuilder::new();
+        for node in self.announce_nodes.iter() {
+            builder.add_node(node.clone());
+        }
+        builder
+            .build(self.timestamp_epoch)
+            .map(MetadataResponsePayload)
+            .map_err(map_js_err)
     }
 }
 
 #[wasm_bindgen]
 pub struct MetadataResponsePayload(nucypher_core::MetadataResponsePayload);
 
+impl AsBackend<nucypher_core::MetadataResponsePayload> for MetadataResponsePayload {
+    fn as_backend(&self) -> &nucypher_core::MetadataResponsePayload {
+        &self.0
+    }
+}
+
+impl FromBackend<nucypher_core::MetadataResponsePayload> for MetadataResponsePayload {
+    fn from_backend(backend: nucypher_core::MetadataResponsePayload) -> Self {
+        MetadataResponsePayload(backend)
+    }
+}
+
 #[wasm_bindgen]
 impl MetadataResponsePayload {
     #[wasm_bindgen(method, getter)]
@@ -1215,6 +2309,35 @@ impl MetadataResponsePayload {
             .map(JsValue::from)
             .collect()
     }
+
+    pub fn domains(&self) -> Vec<JsValue> {
+        self.0.domains().into_iter().map(JsValue::from).collect()
+    }
+
+    #[wasm_bindgen(js_name = isFresherThan)]
+    pub fn is_fresher_than(&self, other: &MetadataResponsePayload) -> bool {
+        self.0.is_fresher_than(&other.0)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(data: &[u8]) -> Result<MetadataResponsePayload, JsValue> {
+        from_bytes(data)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        to_bytes(self)
+    }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> MetadataResponsePayload {
+        Self(self.0.clone())
+    }
 }
 
 //
@@ -1255,6 +2378,34 @@ impl MetadataResponse {
             .map(MetadataResponsePayload)
     }
 
+    #[wasm_bindgen]
+    pub fn signature(&self) -> Box<[u8]> {
+        self.0.signature().to_array().to_vec().into_boxed_slice()
+    }
+
+    #[wasm_bindgen(js_name = signedBytes)]
+    pub fn signed_bytes(&self) -> Box<[u8]> {
+        self.0.signed_bytes()
+    }
+
+    #[wasm_bindgen(js_name = splitBySize)]
+    pub fn split_by_size(
+        &self,
+        signer: &Signer,
+        max_bytes: usize,
+    ) -> Result<Vec<JsValue>, JsValue> {
+        self.0
+            .split_by_size(signer.inner(), max_bytes)
+            .map(|responses| {
+                responses
+                    .into_iter()
+                    .map(MetadataResponse)
+                    .map(JsValue::from)
+                    .collect()
+            })
+            .map_err(map_js_err)
+    }
+
     #[wasm_bindgen(js_name = fromBytes)]
     pub fn from_bytes(data: &[u8]) -> Result<MetadataResponse, JsValue> {
         from_bytes(data)
@@ -1264,4 +2415,81 @@ impl MetadataResponse {
     pub fn to_bytes(&self) -> Box<[u8]> {
         to_bytes(self)
     }
+
+    #[wasm_bindgen(js_name = toBytesInto)]
+    pub fn to_bytes_into(&self, buffer: &mut SerializationBuffer) -> usize {
+        to_bytes_into(self, buffer)
+    }
+
+    #[wasm_bindgen(js_name = clone)]
+    pub fn duplicate(&self) -> MetadataResponse {
+        Self(self.0.clone())
+    }
+}
+
+//
+// Object brands
+//
+
+/// Brands of the serialized protocol objects.
+/// The value of each variant is the 4-byte brand interpreted as a big-endian integer.
+#[wasm_bindgen]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ObjectBrand {
+    MessageKit = 0x4d4b_6974,              // b"MKit"
+    EncryptedKeyFrag = 0x454b_4672,        // b"EKFr"
+    TreasureMap = 0x544d_6170,             // b"TMap"
+    EncryptedTreasureMap = 0x454d_6170,    // b"EMap"
+    TreasureMapArchive = 0x544d_4172,      // b"TMAr"
+    ReencryptionRequest = 0x5265_5271,     // b"ReRq"
+    ReencryptionResponse = 0x5265_5273,    // b"ReRs"
+    RetrievalKit = 0x524b_6974,            // b"RKit"
+    RevocationOrder = 0x5265_766f,         // b"Revo"
+    NodeMetadataPayload = 0x4e64_506c,     // b"NdPl"
+    NodeMetadata = 0x4e64_4d64,            // b"NdMd"
+    MetadataRequest = 0x4d64_5271,         // b"MdRq"
+    MetadataResponsePayload = 0x4d64_506c, // b"MdPl"
+    MetadataResponse = 0x4d64_5273,        // b"MdRs"
+}
+
+const OBJECT_BRANDS: [(ObjectBrand, &str); 14] = [
+    (ObjectBrand::MessageKit, "MessageKit"),
+    (ObjectBrand::EncryptedKeyFrag, "EncryptedKeyFrag"),
+    (ObjectBrand::TreasureMap, "TreasureMap"),
+    (ObjectBrand::EncryptedTreasureMap, "EncryptedTreasureMap"),
+    (ObjectBrand::TreasureMapArchive, "TreasureMapArchive"),
+    (ObjectBrand::ReencryptionRequest, "ReencryptionRequest"),
+    (ObjectBrand::ReencryptionResponse, "ReencryptionResponse"),
+    (ObjectBrand::RetrievalKit, "RetrievalKit"),
+    (ObjectBrand::RevocationOrder, "RevocationOrder"),
+    (ObjectBrand::NodeMetadataPayload, "NodeMetadataPayload"),
+    (ObjectBrand::NodeMetadata, "NodeMetadata"),
+    (ObjectBrand::MetadataRequest, "MetadataRequest"),
+    (
+        ObjectBrand::MetadataResponsePayload,
+        "MetadataResponsePayload",
+    ),
+    (ObjectBrand::MetadataResponse, "MetadataResponse"),
+];
+
+/// Returns the name of the object type serialized in `data`, based on its brand.
+#[wasm_bindgen(js_name = brandOf)]
+pub fn brand_of(data: &[u8]) -> Result<String, JsValue> {
+    let brand_bytes: [u8; 4] = data
+        .get(..4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::new("The data is too short to contain a brand"))?;
+    let brand = u32::from_be_bytes(brand_bytes);
+
+    OBJECT_BRANDS
+        .iter()
+        .find(|(object_brand, _name)| *object_brand as u32 == brand)
+        .map(|(_object_brand, name)| name.to_string())
+        .ok_or_else(|| {
+            Error::new(&format!(
+                "Unknown brand: {}",
+                String::from_utf8_lossy(&brand_bytes)
+            ))
+            .into()
+        })
 }
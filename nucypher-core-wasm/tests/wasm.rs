@@ -1,9 +1,16 @@
-use nucypher_core::Address;
+use js_sys::Uint8Array;
+use nucypher_core::{
+    collect_hracs, split_objects, try_decode_any, Address, DecodedObject, DeserializationError,
+    HasHrac, ProtocolObject, VersionObserver, HEADER_SIZE,
+};
 use nucypher_core_wasm::*;
 
+use sha3::{Digest, Keccak256, Sha3_256};
 use umbral_pre::bindings_wasm::{
-    generate_kfrags, reencrypt, Capsule, SecretKey, Signer, VerifiedCapsuleFrag, VerifiedKeyFrag,
+    generate_kfrags, reencrypt, Capsule, PublicKey, SecretKey, Signer, VerifiedCapsuleFrag,
+    VerifiedKeyFrag,
 };
+use umbral_pre::{DeserializableFromArray, SerializableToArray};
 use wasm_bindgen::convert::FromWasmAbi;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_test::*;
@@ -34,10 +41,18 @@ pub fn verified_key_farg_of_js_value(js_value: JsValue) -> Option<VerifiedKeyFra
     of_js_value_generic(js_value, "VerifiedKeyFrag").unwrap_or(None)
 }
 
+pub fn encrypted_kfrag_of_js_value(js_value: JsValue) -> Option<EncryptedKeyFrag> {
+    of_js_value_generic(js_value, "EncryptedKeyFrag").unwrap_or(None)
+}
+
 pub fn node_metadata_of_js_value(js_value: JsValue) -> Option<NodeMetadata> {
     of_js_value_generic(js_value, "NodeMetadata").unwrap_or(None)
 }
 
+pub fn metadata_response_of_js_value(js_value: JsValue) -> Option<MetadataResponse> {
+    of_js_value_generic(js_value, "MetadataResponse").unwrap_or(None)
+}
+
 fn make_message_kit(sk: &SecretKey, plaintext: &[u8]) -> MessageKit {
     let policy_encrypting_key = sk.public_key();
     MessageKit::new(&policy_encrypting_key, plaintext)
@@ -62,9 +77,10 @@ fn make_kfrags(delegating_sk: &SecretKey, receiving_sk: &SecretKey) -> Vec<Verif
 }
 
 fn make_fleet_state_checksum() -> FleetStateChecksum {
-    let this_node = Some(make_node_metadata());
+    let this_node = make_node_metadata();
     let other_nodes = vec![make_node_metadata(), make_node_metadata()];
-    let mut builder = FleetStateChecksumBuilder::new(this_node);
+    let mut builder = FleetStateChecksumBuilder::new();
+    builder.set_this_node(&this_node);
     for node in &other_nodes {
         builder.add_other_node(node);
     }
@@ -72,13 +88,15 @@ fn make_fleet_state_checksum() -> FleetStateChecksum {
 }
 
 fn make_node_metadata() -> NodeMetadata {
+    make_node_metadata_with(b"00000000000000000001", 1546300800)
+}
+
+fn make_node_metadata_with(staking_provider_address: &[u8], timestamp_epoch: u32) -> NodeMetadata {
     // Just a random valid key.
     // Need to fix it to check the operator address derivation.
     let signing_key = SecretKey::from_bytes(b"01234567890123456789012345678901").unwrap();
 
-    let staking_provider_address = b"00000000000000000001";
     let domain = "localhost";
-    let timestamp_epoch = 1546300800;
     let verifying_key = signing_key.public_key();
     let encrypting_key = SecretKey::random().public_key();
     let certificate_der = b"certificate_der";
@@ -111,7 +129,7 @@ fn make_metadata_response_payload() -> (MetadataResponsePayload, Vec<NodeMetadat
     for node in &announce_nodes {
         payload_builder.add_announce_node(node);
     }
-    (payload_builder.build(), announce_nodes)
+    (payload_builder.build().unwrap(), announce_nodes)
 }
 
 //
@@ -131,6 +149,20 @@ fn message_kit_decrypts() {
     );
 }
 
+#[wasm_bindgen_test]
+fn message_kit_empty_plaintext() {
+    let sk = SecretKey::random();
+
+    // `new()` allows empty plaintexts
+    let message_kit = MessageKit::new(&sk.public_key(), b"");
+    assert!(message_kit.decrypt(&sk).unwrap().is_empty());
+
+    assert!(MessageKit::new_non_empty(&sk.public_key(), b"").is_err());
+
+    let message_kit = MessageKit::new_non_empty(&sk.public_key(), b"Hello, world!").unwrap();
+    assert_eq!(&message_kit.decrypt(&sk).unwrap()[..], b"Hello, world!");
+}
+
 #[wasm_bindgen_test]
 fn message_kit_decrypt_reencrypted() {
     // Create a message kit
@@ -179,6 +211,78 @@ fn message_kit_decrypt_reencrypted() {
     );
 }
 
+#[wasm_bindgen_test]
+fn message_kit_from_retrieval_kit() {
+    let delegating_sk = SecretKey::random();
+    let delegating_pk = delegating_sk.public_key();
+    let plaintext = b"Hello, world!";
+    let message_kit = MessageKit::new(&delegating_pk, plaintext);
+
+    // The retrieval client only keeps the retrieval kit and the ciphertext
+    let retrieval_kit = RetrievalKit::from_message_kit(&message_kit);
+    let ciphertext = message_kit.ciphertext();
+    let restored = MessageKit::from_retrieval_kit(&retrieval_kit, &ciphertext);
+    assert_eq!(restored, message_kit);
+
+    let receiving_sk = SecretKey::random();
+    let verified_kfrags = generate_kfrags(
+        &delegating_sk,
+        &receiving_sk.public_key(),
+        &Signer::new(&delegating_sk),
+        2,
+        3,
+        false,
+        false,
+    );
+    let cfrags: Vec<VerifiedCapsuleFrag> = verified_kfrags
+        .iter()
+        .map(|kfrag| {
+            let kfrag = verified_key_farg_of_js_value(kfrag.clone()).unwrap();
+            reencrypt(&retrieval_kit.capsule(), &kfrag)
+        })
+        .collect();
+
+    let mut mk_with_cfrags = restored.with_cfrag(&cfrags[0]);
+    for cfrag in cfrags.iter().skip(1) {
+        mk_with_cfrags.with_cfrag(cfrag);
+    }
+    let decrypted = mk_with_cfrags
+        .decrypt_reencrypted(&receiving_sk, &delegating_pk)
+        .unwrap();
+    assert_eq!(&decrypted[..], plaintext);
+}
+
+#[wasm_bindgen_test]
+fn message_kit_decrypt_reencrypted_insufficient_cfrags() {
+    let delegating_sk = SecretKey::random();
+    let delegating_pk = delegating_sk.public_key();
+    let receiving_sk = SecretKey::random();
+    let message_kit = MessageKit::new(&delegating_pk, b"Hello, world!");
+
+    // The threshold is 2, but only one cfrag is provided
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+    let cfrag = reencrypt(&message_kit.capsule(), &verified_kfrags[0]);
+    let mk_with_cfrags = message_kit.with_cfrag(&cfrag);
+
+    let err = mk_with_cfrags
+        .decrypt_reencrypted_with_threshold(&receiving_sk, &delegating_pk, 2)
+        .unwrap_err();
+
+    use js_sys::Reflect;
+    assert_eq!(
+        Reflect::get(&err, &"name".into()).unwrap().as_string(),
+        Some("InsufficientCapsuleFrags".into())
+    );
+    assert_eq!(
+        Reflect::get(&err, &"got".into()).unwrap().as_f64(),
+        Some(1.0)
+    );
+    assert_eq!(
+        Reflect::get(&err, &"needed".into()).unwrap().as_f64(),
+        Some(2.0)
+    );
+}
+
 #[wasm_bindgen_test]
 fn message_kit_to_bytes_from_bytes() {
     let sk = SecretKey::random();
@@ -210,6 +314,14 @@ fn hrac_serializes() {
     );
 }
 
+#[wasm_bindgen_test]
+fn hrac_from_bytes_checks_length() {
+    let hrac = make_hrac();
+    assert!(HRAC::from_bytes(&hrac.to_bytes()).unwrap() == hrac);
+    assert!(HRAC::from_bytes(&hrac.to_bytes()[1..]).is_err());
+    assert!(HRAC::from_bytes(&[0u8; 17]).is_err());
+}
+
 //
 // EncryptedKeyFrag
 //
@@ -255,11 +367,130 @@ fn encrypted_to_bytes_from_bytes() {
     );
 }
 
+#[wasm_bindgen_test]
+fn encrypted_kfrag_serialized_len() {
+    let hrac = make_hrac();
+    let delegating_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let receiving_pk = receiving_sk.public_key();
+    let signer = Signer::new(&delegating_sk);
+
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
+
+    assert_eq!(
+        encrypted_kfrag.serialized_len(),
+        encrypted_kfrag.to_bytes().len(),
+        "Serialized length does not match"
+    );
+}
+
+//
+// Versioning
+//
+
+#[wasm_bindgen_test]
+fn header_size() {
+    let message_kit = make_message_kit(&SecretKey::random(), b"Hello, world!");
+    let retrieval_kit = RetrievalKit::from_message_kit(&message_kit);
+
+    // The payload of a retrieval kit without queried addresses is
+    // a MessagePack array marker, the capsule as a bytestring, and an empty array.
+    let capsule_len = message_kit.capsule().inner().to_array().len();
+    let payload_len = 1 + (2 + capsule_len) + 1;
+
+    assert_eq!(retrieval_kit.to_bytes().len() - payload_len, HEADER_SIZE);
+}
+
+#[wasm_bindgen_test]
+fn from_bytes_rejects_other_major_version() {
+    let message_kit = make_message_kit(&SecretKey::random(), b"Hello, world!");
+    let bytes = message_kit.to_bytes();
+
+    // The same major version is accepted
+    assert!(nucypher_core::MessageKit::from_bytes(&bytes).is_ok());
+
+    // The major version is stored in bytes 4..6 of the header
+    let (major, _minor) = <nucypher_core::MessageKit as ProtocolObject>::version();
+    let mut newer = bytes.to_vec();
+    newer[4..6].copy_from_slice(&(major + 1).to_be_bytes());
+
+    match nucypher_core::MessageKit::from_bytes(&newer) {
+        Err(DeserializationError::MajorVersionMismatch { expected, received }) => {
+            assert_eq!(expected, major);
+            assert_eq!(received, major + 1);
+        }
+        _ => panic!("A newer major version was not rejected"),
+    }
+}
+
+#[cfg(feature = "bincode")]
+#[wasm_bindgen_test]
+fn bincode_roundtrip() {
+    let message_kit =
+        nucypher_core::MessageKit::new(SecretKey::random().public_key().inner(), b"Hello");
+    let bytes = message_kit.to_bytes_bincode();
+    assert_eq!(&bytes[..4], b"MKit");
+    assert_eq!(
+        nucypher_core::MessageKit::from_bytes_bincode(&bytes).unwrap(),
+        message_kit
+    );
+
+    let node_metadata = make_node_metadata().inner().clone();
+    let bytes = node_metadata.to_bytes_bincode();
+    assert_eq!(
+        nucypher_core::NodeMetadata::from_bytes_bincode(&bytes).unwrap(),
+        node_metadata
+    );
+
+    // The two formats are not interchangeable
+    assert!(nucypher_core::NodeMetadata::from_bytes(&bytes).is_err());
+
+    // `TreasureMapArchive` goes through its indexed entries in both formats
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let archive = TreasureMapArchiveBuilder::new()
+        .add_treasure_map(&make_treasure_map(&publisher_sk, &receiving_sk))
+        .build();
+    let archive = nucypher_core::TreasureMapArchive::from_bytes(&archive.to_bytes()).unwrap();
+    let bytes = archive.to_bytes_bincode();
+    assert_eq!(
+        nucypher_core::TreasureMapArchive::from_bytes_bincode(&bytes).unwrap(),
+        archive
+    );
+}
+
+#[cfg(feature = "bincode")]
+#[wasm_bindgen_test]
+fn bincode_validation() {
+    // The same checks as for the MessagePack payload are applied
+    let fleet_state_checksum =
+        nucypher_core::FleetStateChecksum::from_slice(&make_fleet_state_checksum().to_bytes())
+            .unwrap();
+    let nodes = vec![make_node_metadata().inner().clone(); nucypher_core::MAX_ANNOUNCE_NODES + 1];
+    let request = nucypher_core::MetadataRequest::new_with_max_nodes(
+        &fleet_state_checksum,
+        &nodes,
+        usize::MAX,
+    )
+    .unwrap();
+    let bytes = request.to_bytes_bincode();
+    assert!(nucypher_core::MetadataRequest::from_bytes_bincode(&bytes).is_err());
+}
+
 //
 // TreasureMap
 //
 
 fn make_treasure_map(publisher_sk: &SecretKey, receiving_sk: &SecretKey) -> TreasureMap {
+    make_treasure_map_with_expiration(publisher_sk, receiving_sk, None)
+}
+
+fn make_treasure_map_with_expiration(
+    publisher_sk: &SecretKey,
+    receiving_sk: &SecretKey,
+    expiration_epoch: Option<u32>,
+) -> TreasureMap {
     let hrac = make_hrac();
     let vkfrags = make_kfrags(publisher_sk, receiving_sk);
 
@@ -278,6 +509,10 @@ fn make_treasure_map(publisher_sk: &SecretKey, receiving_sk: &SecretKey) -> Trea
     )
     .unwrap();
 
+    if let Some(expiration_epoch) = expiration_epoch {
+        builder.set_expiration_epoch(expiration_epoch);
+    }
+
     // Also try using the consuming variant of builder:
     builder
         .add_kfrag(
@@ -315,6 +550,130 @@ fn treasure_map_encrypt_decrypt() {
     );
 }
 
+#[wasm_bindgen_test]
+fn treasure_map_clone() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    let cloned = treasure_map.duplicate();
+    assert_eq!(cloned, treasure_map, "Cloned TreasureMap does not match");
+
+    // Both copies remain usable after one of them is consumed
+    let signer = Signer::new(&publisher_sk);
+    let encrypted = cloned.encrypt(&signer, &receiving_sk.public_key());
+    drop(cloned);
+    let decrypted = encrypted
+        .decrypt(&receiving_sk, &publisher_sk.public_key())
+        .unwrap();
+    assert_eq!(
+        decrypted, treasure_map,
+        "Decrypted TreasureMap does not match"
+    );
+    assert_eq!(treasure_map.to_bytes(), decrypted.to_bytes());
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_responses_still_needed() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+
+    // Threshold is 2
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert_eq!(treasure_map.responses_still_needed(0), 2);
+    assert_eq!(treasure_map.responses_still_needed(1), 1);
+    assert_eq!(treasure_map.responses_still_needed(2), 0);
+    assert_eq!(treasure_map.responses_still_needed(3), 0);
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_recommended_contact_count() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+
+    // Threshold is 2, there are 3 destinations
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert_eq!(treasure_map.recommended_contact_count(0.0), 2);
+    // ceil(2 / 0.75) = 3
+    assert_eq!(treasure_map.recommended_contact_count(0.25), 3);
+    // ceil(2 / 0.5) = 4, limited by the number of destinations
+    assert_eq!(treasure_map.recommended_contact_count(0.5), 3);
+    assert_eq!(treasure_map.recommended_contact_count(1.0), 3);
+    assert_eq!(treasure_map.recommended_contact_count(f32::NAN), 3);
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_expiration_epoch() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+
+    let treasure_map =
+        make_treasure_map_with_expiration(&publisher_sk, &receiving_sk, Some(1546300800));
+    assert_eq!(treasure_map.expiration_epoch(), Some(1546300800));
+
+    let restored = TreasureMap::from_bytes(&treasure_map.to_bytes()).unwrap();
+    assert_eq!(restored, treasure_map, "TreasureMap does not roundtrip");
+
+    assert!(!treasure_map.is_expired(1546300799));
+    // The map is already expired at the expiration epoch itself
+    assert!(treasure_map.is_expired(1546300800));
+    assert!(treasure_map.is_expired(1546300801));
+
+    // A map without the expiration epoch never expires
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert_eq!(treasure_map.expiration_epoch(), None);
+    assert!(!treasure_map.is_expired(u32::MAX));
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_from_version_1_0() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+
+    // Convert the serialized map to version 1.0 by dropping `expiration_epoch`:
+    // the struct is serialized as an array, and `None` is serialized as `nil` at the end.
+    let mut bytes = treasure_map.to_bytes().to_vec();
+    assert_eq!(&bytes[4..8], &[0, 1, 0, 1]);
+    bytes[7] = 0;
+    assert_eq!(bytes[8], 0x96);
+    bytes[8] = 0x95;
+    assert_eq!(bytes.pop(), Some(0xc0));
+
+    let restored = TreasureMap::from_bytes(&bytes).unwrap();
+    assert_eq!(restored, treasure_map, "TreasureMap 1.0 is not decoded");
+    assert_eq!(restored.expiration_epoch(), None);
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_migrate() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    let current_bytes = treasure_map.to_bytes();
+
+    // Version 1.0 of the same map (see `treasure_map_from_version_1_0`)
+    let mut old_bytes = current_bytes.to_vec();
+    old_bytes[7] = 0;
+    old_bytes[8] = 0x95;
+    old_bytes.pop();
+
+    let migrated = nucypher_core::TreasureMap::migrate(&old_bytes).unwrap();
+    assert_eq!(&migrated[4..8], &[0, 1, 0, 1]);
+    assert_eq!(migrated, current_bytes);
+
+    // Maps at the current version are unchanged
+    assert_eq!(
+        nucypher_core::TreasureMap::migrate(&current_bytes).unwrap(),
+        current_bytes
+    );
+
+    // The major version cannot be migrated
+    let mut future_bytes = current_bytes.to_vec();
+    future_bytes[5] = 2;
+    assert!(nucypher_core::TreasureMap::migrate(&future_bytes).is_err());
+}
+
 #[wasm_bindgen_test]
 fn treasure_map_destinations() {
     let publisher_sk = SecretKey::random();
@@ -336,133 +695,1022 @@ fn treasure_map_destinations() {
 }
 
 #[wasm_bindgen_test]
-fn encrypted_treasure_map_from_bytes_to_bytes() {
+fn treasure_map_destinations_map() {
     let publisher_sk = SecretKey::random();
     let receiving_sk = SecretKey::random();
-    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
 
-    let encrypted = treasure_map.encrypt(&Signer::new(&publisher_sk), &receiving_sk.public_key());
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    let destinations: Vec<(Address, EncryptedKeyFrag)> =
+        serde_wasm_bindgen::from_value(treasure_map.destinations().unwrap()).unwrap();
+    let destinations_map = treasure_map.destinations_map();
 
     assert_eq!(
-        encrypted,
-        EncryptedTreasureMap::from_bytes(&encrypted.to_bytes()).unwrap(),
-        "EncryptedTreasureMap does not roundtrip"
+        destinations_map.size(),
+        3,
+        "Destinations map size does not match"
     );
+    for (address, ekfrag) in destinations {
+        let key = JsValue::from(address.to_checksum_address());
+        assert!(destinations_map.has(&key), "Destination address is missing");
+        let value = encrypted_kfrag_of_js_value(destinations_map.get(&key)).unwrap();
+        assert_eq!(value, ekfrag, "Destination kfrag does not match");
+    }
 }
 
-//
-// ReencryptionRequest
-//
-
 #[wasm_bindgen_test]
-fn reencryption_request_from_bytes_to_bytes() {
-    // Make capsules
-    let publisher_sk = SecretKey::random();
-    let policy_encrypting_key = publisher_sk.public_key();
-    let plaintext = b"Hello, world!";
-    let message_kit = MessageKit::new(&policy_encrypting_key, plaintext);
-    let capsules = vec![message_kit.capsule()];
-
-    let hrac = make_hrac();
-
-    // Make encrypted key frag
-    let receiving_sk = SecretKey::random();
-    let receiving_pk = receiving_sk.public_key();
-    let signer = Signer::new(&publisher_sk);
-    let verified_kfrags = make_kfrags(&publisher_sk, &receiving_sk);
-    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
-
-    // Make reencryption request
-    let reencryption_request = ReencryptionRequestBuilder::new(
-        &hrac,
-        &encrypted_kfrag,
-        &publisher_sk.public_key(),
-        &receiving_pk,
-    )
-    .unwrap()
-    .add_capsule(&capsules[0])
-    .build();
+fn address_from_slice() {
+    let address = Address::from_slice(b"00000000000000000001").unwrap();
+    assert_eq!(address.as_ref(), b"00000000000000000001");
 
+    let err = Address::from_slice(b"0000000000000000001").unwrap_err();
     assert_eq!(
-        reencryption_request,
-        ReencryptionRequest::from_bytes(&reencryption_request.to_bytes()).unwrap(),
-        "ReencryptionRequest does not roundtrip"
-    )
+        err,
+        nucypher_core::LengthMismatchError {
+            expected: 20,
+            received: 19
+        }
+    );
+    assert!(Address::try_from(&b"000000000000000000001"[..]).is_err());
 }
 
-//
-// ReencryptionResponse
-//
+#[wasm_bindgen_test]
+fn address_to_checksum_address() {
+    // Test vector from EIP-55
+    let address = Address::new(&[
+        0x5a, 0xae, 0xb6, 0x05, 0x3f, 0x3e, 0x94, 0xc9, 0xb9, 0xa0, 0x9f, 0x33, 0x66, 0x94, 0x35,
+        0xe7, 0xef, 0x1b, 0xea, 0xed,
+    ]);
+    assert_eq!(
+        address.to_checksum_address(),
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+}
 
 #[wasm_bindgen_test]
-fn reencryption_response_verify() {
-    // First, we're going to create a reencryption response
-    // This response is created by the network and received by the network client
+fn address_h160_conversion() {
+    let h160 = ethereum_types::H160::from_slice(b"00000000000000000001");
 
-    let alice_sk = SecretKey::random();
-    let bob_sk = SecretKey::random();
+    let address = Address::from(h160);
+    assert_eq!(address.as_ref(), h160.as_bytes());
 
-    // Make verified key fragments
-    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let h160_back: ethereum_types::H160 = address.into();
+    assert_eq!(h160_back, h160);
+}
 
-    // Make capsules
-    let policy_encrypting_key = alice_sk.public_key();
-    let plaintext = b"Hello, world!";
-    let message_kit = MessageKit::new(&policy_encrypting_key, plaintext);
-    let capsules: Vec<Capsule> = kfrags.iter().map(|_| message_kit.capsule()).collect();
+#[wasm_bindgen_test]
+fn encrypted_treasure_map_decrypt_available() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
 
-    assert_eq!(capsules.len(), kfrags.len());
+    let encrypted = treasure_map.encrypt(&Signer::new(&publisher_sk), &receiving_sk.public_key());
 
-    // Simulate the reencryption
-    let cfrags: Vec<VerifiedCapsuleFrag> = kfrags
+    let reachable: Vec<JsValue> = [b"00000000000000000001", b"00000000000000000003"]
         .iter()
-        .map(|kfrag| reencrypt(&capsules[0], kfrag))
+        .map(|address| js_sys::Uint8Array::from(&address[..]).into())
         .collect();
+    let available = encrypted
+        .decrypt_available(&receiving_sk, &publisher_sk.public_key(), reachable)
+        .unwrap();
+    let available: Vec<(Address, EncryptedKeyFrag)> =
+        serde_wasm_bindgen::from_value(available).unwrap();
 
-    // Make the reencryption response
-    let ursula_sk = SecretKey::random();
-    let signer = Signer::new(&ursula_sk);
-    let mut builder = ReencryptionResponseBuilder::new(&signer);
-    for cfrag in &cfrags {
-        builder.add_cfrag(cfrag);
+    assert_eq!(available.len(), 2, "Available destinations do not match");
+    assert_eq!(available[0].0.as_ref(), b"00000000000000000001");
+    assert_eq!(available[1].0.as_ref(), b"00000000000000000003");
+}
+
+#[wasm_bindgen_test]
+fn encrypted_treasure_map_from_bytes_to_bytes() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+
+    let encrypted = treasure_map.encrypt(&Signer::new(&publisher_sk), &receiving_sk.public_key());
+
+    assert_eq!(
+        encrypted,
+        EncryptedTreasureMap::from_bytes(&encrypted.to_bytes()).unwrap(),
+        "EncryptedTreasureMap does not roundtrip"
+    );
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_serialized_len() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert_eq!(
+        treasure_map.serialized_len(),
+        treasure_map.to_bytes().len(),
+        "Serialized length does not match"
+    );
+
+    let treasure_map =
+        make_treasure_map_with_expiration(&publisher_sk, &receiving_sk, Some(1546300800));
+    assert_eq!(
+        treasure_map.serialized_len(),
+        treasure_map.to_bytes().len(),
+        "Serialized length does not match"
+    );
+}
+
+#[wasm_bindgen_test]
+fn version_observer() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+
+    // `TreasureMap` is currently at version 1.1, and 1.0 is still supported
+    let current = treasure_map.to_bytes();
+    let mut older = current.to_vec();
+    older[6..8].copy_from_slice(&0u16.to_be_bytes());
+
+    let mut observer = VersionObserver::new();
+    assert_eq!(observer.min_seen(b"TMap"), None);
+
+    observer.observe(&current).unwrap();
+    observer.observe(&older).unwrap();
+    observer.observe(&current).unwrap();
+    observer.observe(&make_node_metadata().to_bytes()).unwrap();
+
+    assert_eq!(observer.min_seen(b"TMap"), Some((1, 0)));
+    assert_eq!(observer.max_seen(b"TMap"), Some((1, 1)));
+    assert_eq!(observer.min_seen(b"NdMd"), observer.max_seen(b"NdMd"));
+    assert_eq!(observer.max_seen(b"MKit"), None);
+
+    assert!(observer.observe(b"TMap").is_err());
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_same_policy_as() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let signer = Signer::new(&publisher_sk);
+    let hrac = make_hrac();
+    let policy_encrypting_key = SecretKey::random().public_key();
+    let vkfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let ursula_keys: Vec<PublicKey> = vkfrags
+        .iter()
+        .map(|_| SecretKey::random().public_key())
+        .collect();
+
+    let make_map = || {
+        let mut builder =
+            TreasureMapBuilder::new(&signer, &hrac, &policy_encrypting_key, 2).unwrap();
+        for (i, (vkfrag, ursula_key)) in vkfrags.iter().zip(ursula_keys.iter()).enumerate() {
+            let address = format!("0000000000000000000{}", i + 1);
+            builder
+                .add_kfrag(address.as_bytes(), ursula_key, vkfrag)
+                .unwrap();
+        }
+        builder.build()
+    };
+
+    let map1 = make_map();
+    let map2 = make_map();
+
+    // The encryption of kfrags is randomized
+    assert_ne!(map1.to_bytes(), map2.to_bytes());
+    assert!(map1.same_policy_as(&map2));
+
+    let other_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert!(!map1.same_policy_as(&other_map));
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_encrypted_kfrag_for() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+
+    assert_eq!(treasure_map.destination_count(), 3);
+
+    let ekfrag = treasure_map
+        .encrypted_kfrag_for(b"00000000000000000002")
+        .unwrap()
+        .unwrap();
+    let backend = nucypher_core::TreasureMap::from_bytes(&treasure_map.to_bytes()).unwrap();
+    let address = Address::from_slice(b"00000000000000000002").unwrap();
+    assert_eq!(
+        ekfrag.to_bytes(),
+        backend.destinations[&address].to_bytes(),
+        "Encrypted kfrag does not match"
+    );
+
+    assert!(treasure_map
+        .encrypted_kfrag_for(b"00000000000000000004")
+        .unwrap()
+        .is_none());
+    assert!(treasure_map
+        .encrypted_kfrag_for(b"0000000000000000002")
+        .is_err());
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_validate() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert!(treasure_map.validate().is_ok());
+
+    let backend = nucypher_core::TreasureMap::from_bytes(&treasure_map.to_bytes()).unwrap();
+    assert_eq!(backend.validate(), Ok(()));
+
+    // The map has 3 destinations, so the largest valid threshold is 3.
+    let mut backend_all = backend.clone();
+    backend_all.threshold = 3;
+    assert_eq!(backend_all.validate(), Ok(()));
+    assert!(TreasureMap::from_bytes(&backend_all.to_bytes()).is_ok());
+
+    let mut backend_zero = backend.clone();
+    backend_zero.threshold = 0;
+    assert_eq!(
+        backend_zero.validate(),
+        Err(nucypher_core::ValidationError::ZeroThreshold)
+    );
+    let err = TreasureMap::from_bytes(&backend_zero.to_bytes()).unwrap_err();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert!(message.contains("threshold must be non-zero"));
+
+    let mut backend_large = backend;
+    backend_large.threshold = 4;
+    assert_eq!(
+        backend_large.validate(),
+        Err(nucypher_core::ValidationError::ThresholdTooLarge {
+            threshold: 4,
+            destinations: 3
+        })
+    );
+    let err = TreasureMap::from_bytes(&backend_large.to_bytes()).unwrap_err();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert!(message.contains("threshold (4) is larger than the number of destinations (3)"));
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_builder_rejects_wrong_address() {
+    let publisher_sk = SecretKey::random();
+    let vkfrags = make_kfrags(&publisher_sk, &SecretKey::random());
+    let mut builder = TreasureMapBuilder::new(
+        &Signer::new(&publisher_sk),
+        &make_hrac(),
+        &SecretKey::random().public_key(),
+        2,
+    )
+    .unwrap();
+
+    let err = builder
+        .add_kfrag(
+            b"0000000000000000001",
+            &SecretKey::random().public_key(),
+            &vkfrags[0],
+        )
+        .err()
+        .unwrap();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert!(message.contains("Incorrect address size: 19"));
+    assert!(message.contains("0x30303030303030303030303030303030303031"));
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_destinations_chain_ordered() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let vkfrags = make_kfrags(&publisher_sk, &receiving_sk);
+
+    // Added out of order, and differing in the first and the last bytes
+    let addresses: [&[u8; 20]; 3] = [
+        b"\x020000000000000000000",
+        b"\x010000000000000000009",
+        b"\x010000000000000000001",
+    ];
+    let mut builder = TreasureMapBuilder::new(
+        &Signer::new(&publisher_sk),
+        &make_hrac(),
+        &SecretKey::random().public_key(),
+        2,
+    )
+    .unwrap();
+    for (address, vkfrag) in addresses.iter().zip(vkfrags.iter()) {
+        builder
+            .add_kfrag(&address[..], &SecretKey::random().public_key(), vkfrag)
+            .unwrap();
+    }
+    let treasure_map = builder.build();
+    let backend = nucypher_core::TreasureMap::from_bytes(&treasure_map.to_bytes()).unwrap();
+
+    let mut expected = addresses.to_vec();
+    expected.sort_by_key(|address| ethereum_types::U256::from_big_endian(&address[..]));
+
+    let ordered: Vec<[u8; 20]> = backend
+        .destinations_chain_ordered()
+        .iter()
+        .map(|(address, _ekfrag)| (*address).into())
+        .collect();
+    let expected: Vec<[u8; 20]> = expected.into_iter().copied().collect();
+    assert_eq!(ordered, expected);
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_archive() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_maps: Vec<TreasureMap> = (0..3)
+        .map(|_| make_treasure_map(&publisher_sk, &receiving_sk))
+        .collect();
+
+    let mut builder = TreasureMapArchiveBuilder::new();
+    for treasure_map in treasure_maps.iter() {
+        builder.add_treasure_map(treasure_map);
+    }
+    let archive = builder.build();
+
+    let as_bytes = archive.to_bytes();
+    assert_eq!(brand_of(&as_bytes).unwrap(), "TreasureMapArchive");
+    let archive_back = TreasureMapArchive::from_bytes(&as_bytes).unwrap();
+    assert_eq!(
+        archive_back, archive,
+        "TreasureMapArchive does not roundtrip"
+    );
+    assert_eq!(archive_back.treasure_maps().len(), 3);
+
+    let treasure_map = archive_back.get(&treasure_maps[1].hrac()).unwrap();
+    assert_eq!(treasure_map, treasure_maps[1]);
+
+    assert!(archive_back.get(&make_hrac()).is_none());
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_store() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_maps: Vec<TreasureMap> = (0..3)
+        .map(|_| make_treasure_map(&publisher_sk, &receiving_sk))
+        .collect();
+
+    let mut store = TreasureMapStore::new();
+    for treasure_map in treasure_maps.iter() {
+        assert!(store.insert(treasure_map).is_none());
+    }
+    assert_eq!(store.size(), 3);
+
+    for treasure_map in treasure_maps.iter() {
+        let hrac = treasure_map.hrac();
+        assert!(store.contains(&hrac));
+        assert_eq!(&store.get(&hrac).unwrap(), treasure_map);
+    }
+
+    let unknown_hrac = make_hrac();
+    assert!(!store.contains(&unknown_hrac));
+    assert!(store.get(&unknown_hrac).is_none());
+
+    // Inserting a map with the same HRAC replaces the old one
+    let replaced = store.insert(&treasure_maps[1]).unwrap();
+    assert_eq!(replaced, treasure_maps[1]);
+    assert_eq!(store.size(), 3);
+
+    let removed = store.remove(&treasure_maps[0].hrac()).unwrap();
+    assert_eq!(removed, treasure_maps[0]);
+    assert!(!store.contains(&treasure_maps[0].hrac()));
+    assert!(store.remove(&treasure_maps[0].hrac()).is_none());
+    assert_eq!(store.size(), 2);
+}
+
+#[wasm_bindgen_test]
+fn treasure_map_policy_fingerprint() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    let signer = Signer::new(&publisher_sk);
+
+    // Encrypt the same map for two different recipients
+    let first_sk = SecretKey::random();
+    let second_sk = SecretKey::random();
+    let first = treasure_map
+        .encrypt(&signer, &first_sk.public_key())
+        .decrypt(&first_sk, &publisher_sk.public_key())
+        .unwrap();
+    let second = treasure_map
+        .encrypt(&signer, &second_sk.public_key())
+        .decrypt(&second_sk, &publisher_sk.public_key())
+        .unwrap();
+
+    let fingerprint = treasure_map.policy_fingerprint();
+    assert_eq!(fingerprint.len(), 32);
+    assert_eq!(first.policy_fingerprint(), fingerprint);
+    assert_eq!(second.policy_fingerprint(), fingerprint);
+
+    // Two maps built independently for the same policy have the same fingerprint,
+    // even though their encrypted kfrags differ
+    let hrac = make_hrac();
+    let policy_encrypting_key = SecretKey::random().public_key();
+    let vkfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let ursula_keys: Vec<PublicKey> = (0..3).map(|_| SecretKey::random().public_key()).collect();
+    let build_map = || {
+        let mut builder =
+            TreasureMapBuilder::new(&signer, &hrac, &policy_encrypting_key, 2).unwrap();
+        for (i, (ursula_key, vkfrag)) in ursula_keys.iter().zip(vkfrags.iter()).enumerate() {
+            let address = format!("0000000000000000000{}", i + 1);
+            builder
+                .add_kfrag(address.as_bytes(), ursula_key, vkfrag)
+                .unwrap();
+        }
+        builder.build()
+    };
+    let map1 = build_map();
+    let map2 = build_map();
+    assert!(map1 != map2);
+    assert!(map1.same_policy_as(&map2));
+    assert_eq!(map1.policy_fingerprint(), map2.policy_fingerprint());
+
+    // A different map has a different fingerprint
+    let other_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert_ne!(other_map.policy_fingerprint(), fingerprint);
+}
+
+//
+// ReencryptionRequest
+//
+
+#[wasm_bindgen_test]
+fn reencryption_request_from_bytes_to_bytes() {
+    // Make capsules
+    let publisher_sk = SecretKey::random();
+    let policy_encrypting_key = publisher_sk.public_key();
+    let plaintext = b"Hello, world!";
+    let message_kit = MessageKit::new(&policy_encrypting_key, plaintext);
+    let capsules = vec![message_kit.capsule()];
+
+    let hrac = make_hrac();
+
+    // Make encrypted key frag
+    let receiving_sk = SecretKey::random();
+    let receiving_pk = receiving_sk.public_key();
+    let signer = Signer::new(&publisher_sk);
+    let verified_kfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
+
+    // Make reencryption request
+    let reencryption_request = ReencryptionRequestBuilder::new(
+        &hrac,
+        &encrypted_kfrag,
+        &publisher_sk.public_key(),
+        &receiving_pk,
+    )
+    .unwrap()
+    .add_capsule(&capsules[0])
+    .build();
+
+    assert_eq!(
+        reencryption_request,
+        ReencryptionRequest::from_bytes(&reencryption_request.to_bytes()).unwrap(),
+        "ReencryptionRequest does not roundtrip"
+    )
+}
+
+#[wasm_bindgen_test]
+fn reencryption_request_capsule_at() {
+    let publisher_sk = SecretKey::random();
+    let policy_encrypting_key = publisher_sk.public_key();
+    let capsules = [
+        MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule(),
+        MessageKit::new(&policy_encrypting_key, b"Goodbye, world!").capsule(),
+    ];
+
+    let hrac = make_hrac();
+    let receiving_sk = SecretKey::random();
+    let receiving_pk = receiving_sk.public_key();
+    let signer = Signer::new(&publisher_sk);
+    let verified_kfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
+
+    let reencryption_request = ReencryptionRequestBuilder::new(
+        &hrac,
+        &encrypted_kfrag,
+        &publisher_sk.public_key(),
+        &receiving_pk,
+    )
+    .unwrap()
+    .add_capsule(&capsules[0])
+    .add_capsule(&capsules[1])
+    .build();
+
+    assert_eq!(reencryption_request.capsule_count(), 2);
+    assert!(reencryption_request.capsule_at(1).unwrap().inner() == capsules[1].inner());
+    assert!(reencryption_request.capsule_at(2).is_err());
+}
+
+#[wasm_bindgen_test]
+fn collect_hracs_from_mixed_objects() {
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let receiving_pk = receiving_sk.public_key();
+    let signer = Signer::new(&publisher_sk);
+    let verified_kfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let capsule = MessageKit::new(&publisher_sk.public_key(), b"Hello, world!").capsule();
+
+    let make_request = |hrac: &HRAC| {
+        let encrypted_kfrag =
+            EncryptedKeyFrag::new(&signer, &receiving_pk, hrac, &verified_kfrags[0]);
+        let request = ReencryptionRequestBuilder::new(
+            hrac,
+            &encrypted_kfrag,
+            &publisher_sk.public_key(),
+            &receiving_pk,
+        )
+        .unwrap()
+        .add_capsule(&capsule)
+        .build();
+        nucypher_core::ReencryptionRequest::from_bytes(&request.to_bytes()).unwrap()
+    };
+
+    let hrac1 = make_hrac();
+    let hrac2 = make_hrac();
+    let request1 = make_request(&hrac1);
+    let request2 = make_request(&hrac2);
+    let request3 = make_request(&hrac1);
+
+    let encrypted_kfrag =
+        EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac2, &verified_kfrags[1]);
+    let revocation_order =
+        RevocationOrder::new(&signer, b"00000000000000000001", &encrypted_kfrag).unwrap();
+    let revocation_order =
+        nucypher_core::RevocationOrder::from_bytes(&revocation_order.to_bytes()).unwrap();
+
+    let objects: [&dyn HasHrac; 4] = [&request1, &revocation_order, &request2, &request3];
+    let hracs = collect_hracs(&objects);
+
+    let expected: Vec<Box<[u8]>> = vec![hrac1.to_bytes(), hrac2.to_bytes()];
+    let hracs: Vec<Box<[u8]>> = hracs
+        .iter()
+        .map(|hrac| hrac.as_ref().to_vec().into_boxed_slice())
+        .collect();
+    assert_eq!(hracs, expected, "Collected HRACs do not match");
+}
+
+#[wasm_bindgen_test]
+fn reencryption_request_precheck() {
+    let publisher_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let ursula_sk = SecretKey::random();
+    let other_ursula_sk = SecretKey::random();
+
+    let hrac = make_hrac();
+    let signer = Signer::new(&publisher_sk);
+    let verified_kfrags = make_kfrags(&publisher_sk, &bob_sk);
+    let capsule = MessageKit::new(&publisher_sk.public_key(), b"Hello, world!").capsule();
+
+    let make_request = |ursula_pk: &PublicKey| {
+        let encrypted_kfrag = EncryptedKeyFrag::new(&signer, ursula_pk, &hrac, &verified_kfrags[0]);
+        ReencryptionRequestBuilder::new(
+            &hrac,
+            &encrypted_kfrag,
+            &publisher_sk.public_key(),
+            &bob_sk.public_key(),
+        )
+        .unwrap()
+        .add_capsule(&capsule)
+        .build()
+    };
+
+    let request = make_request(&ursula_sk.public_key());
+    assert!(request.precheck(&ursula_sk).is_ok());
+
+    // The kfrag is encrypted for a different Ursula
+    let request = make_request(&other_ursula_sk.public_key());
+    let err = request.precheck(&ursula_sk).unwrap_err();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert!(
+        message.starts_with("decryption failed"),
+        "Unexpected error: {}",
+        message
+    );
+}
+
+//
+// ReencryptionResponse
+//
+
+#[wasm_bindgen_test]
+fn reencryption_response_verify() {
+    // First, we're going to create a reencryption response
+    // This response is created by the network and received by the network client
+
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+
+    // Make verified key fragments
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+
+    // Make capsules
+    let policy_encrypting_key = alice_sk.public_key();
+    let plaintext = b"Hello, world!";
+    let message_kit = MessageKit::new(&policy_encrypting_key, plaintext);
+    let capsules: Vec<Capsule> = kfrags.iter().map(|_| message_kit.capsule()).collect();
+
+    assert_eq!(capsules.len(), kfrags.len());
+
+    // Simulate the reencryption
+    let cfrags: Vec<VerifiedCapsuleFrag> = kfrags
+        .iter()
+        .map(|kfrag| reencrypt(&capsules[0], kfrag))
+        .collect();
+
+    // Make the reencryption response
+    let ursula_sk = SecretKey::random();
+    let signer = Signer::new(&ursula_sk);
+    let mut builder = ReencryptionResponseBuilder::new(&signer);
+    for cfrag in &cfrags {
+        builder.add_cfrag(cfrag);
     }
     for capsule in &capsules {
         builder.add_capsule(capsule);
     }
     let reencryption_response = builder.build();
 
-    // Now that the response is created, we're going to "send it" to the client and verify it
+    // Now that the response is created, we're going to "send it" to the client and verify it
+
+    // Add capsule to reencryption response
+    let mut resp_with_capsules = reencryption_response.with_capsule(&capsules[0]);
+    for capsule in &capsules[1..] {
+        resp_with_capsules = resp_with_capsules.with_capsule(capsule);
+    }
+
+    // Verify reencryption response
+    let verified_js = resp_with_capsules
+        .verify(
+            &alice_sk.public_key(),
+            &ursula_sk.public_key(),
+            &policy_encrypting_key,
+            &bob_sk.public_key(),
+        )
+        .unwrap();
+    let verified: Vec<VerifiedCapsuleFrag> = verified_js
+        .iter()
+        .map(|vkfrag| vkfrag.into_serde().unwrap())
+        .collect();
+
+    assert_eq!(cfrags, verified, "Capsule fragments do not match");
+
+    let as_bytes = reencryption_response.to_bytes();
+    assert_eq!(
+        as_bytes,
+        ReencryptionResponse::from_bytes(&as_bytes)
+            .unwrap()
+            .to_bytes(),
+        "ReencryptionResponse does not roundtrip"
+    );
+}
+
+#[wasm_bindgen_test]
+fn reencryption_response_verify_invalid_cfrag() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
+
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule();
+    let other_capsule = MessageKit::new(&policy_encrypting_key, b"Goodbye, world!").capsule();
+
+    // The cfrag does not correspond to the capsule it is supposed to be reencrypting
+    let ursula_sk = SecretKey::random();
+    let mut builder = ReencryptionResponseBuilder::new(&Signer::new(&ursula_sk));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&other_capsule, &kfrags[0]));
+    let reencryption_response = builder.build();
+
+    let err = reencryption_response
+        .with_capsule(&capsule)
+        .verify(
+            &alice_sk.public_key(),
+            &ursula_sk.public_key(),
+            &policy_encrypting_key,
+            &bob_sk.public_key(),
+        )
+        .unwrap_err();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert_eq!(message, "ReencryptionResponse verification failed");
+}
+
+#[wasm_bindgen_test]
+fn reencryption_response_from_cfrag_bytes() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
+
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule();
+    let cfrags: Vec<VerifiedCapsuleFrag> = kfrags
+        .iter()
+        .map(|kfrag| reencrypt(&capsule, kfrag))
+        .collect();
+
+    // The Ursula stores the cfrags as bytes
+    let cfrags_bytes: Vec<JsValue> = cfrags
+        .iter()
+        .map(|cfrag| Uint8Array::from(cfrag.inner().to_array().as_ref()).into())
+        .collect();
+
+    let ursula_sk = SecretKey::random();
+    let mut builder = ReencryptionResponseBuilder::new(&Signer::new(&ursula_sk));
+    for _cfrag in &cfrags {
+        builder.add_capsule(&capsule);
+    }
+    let reencryption_response = builder
+        .build_from_cfrag_bytes(cfrags_bytes.clone())
+        .unwrap();
+
+    let mut resp_with_capsules = reencryption_response.with_capsule(&capsule);
+    for _cfrag in &cfrags[1..] {
+        resp_with_capsules = resp_with_capsules.with_capsule(&capsule);
+    }
+    let verified = resp_with_capsules
+        .verify(
+            &alice_sk.public_key(),
+            &ursula_sk.public_key(),
+            &policy_encrypting_key,
+            &bob_sk.public_key(),
+        )
+        .unwrap();
+    assert_eq!(verified.len(), cfrags.len());
+
+    // Malformed cfrag bytes are rejected
+    let malformed_bytes: Vec<JsValue> = cfrags
+        .iter()
+        .map(|_cfrag| Uint8Array::from(&b"not a cfrag"[..]).into())
+        .collect();
+    assert!(builder.build_from_cfrag_bytes(malformed_bytes).is_err());
+
+    // The number of cfrags must match the number of capsules
+    let err = builder
+        .build_from_cfrag_bytes(cfrags_bytes[..1].to_vec())
+        .err()
+        .unwrap();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert_eq!(
+        message,
+        "the number of capsule frags (1) differs from the number of capsules (3)"
+    );
+}
+
+#[wasm_bindgen_test]
+fn reencryption_response_filter_valid() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
 
-    // Add capsule to reencryption response
-    let mut resp_with_capsules = reencryption_response.with_capsule(&capsules[0]);
-    for capsule in &capsules[1..] {
-        resp_with_capsules = resp_with_capsules.with_capsule(capsule);
-    }
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule();
+    let other_capsule = MessageKit::new(&policy_encrypting_key, b"Goodbye, world!").capsule();
 
-    // Verify reencryption response
-    let verified_js = resp_with_capsules
+    // The second cfrag is made for a different capsule, so it will not verify
+    let ursula_sk = SecretKey::random();
+    let mut builder = ReencryptionResponseBuilder::new(&Signer::new(&ursula_sk));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&capsule, &kfrags[0]));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&other_capsule, &kfrags[1]));
+    let reencryption_response = builder.build();
+
+    let resp_with_capsules = reencryption_response
+        .with_capsule(&capsule)
+        .with_capsule(&capsule);
+    assert!(resp_with_capsules
         .verify(
             &alice_sk.public_key(),
             &ursula_sk.public_key(),
             &policy_encrypting_key,
             &bob_sk.public_key(),
         )
+        .is_err());
+
+    let client_sk = SecretKey::random();
+    let filtered = resp_with_capsules
+        .filter_valid(
+            &Signer::new(&client_sk),
+            &alice_sk.public_key(),
+            &ursula_sk.public_key(),
+            &policy_encrypting_key,
+            &bob_sk.public_key(),
+        )
         .unwrap();
-    let verified: Vec<VerifiedCapsuleFrag> = verified_js
+
+    assert_eq!(filtered.capsules().len(), 1);
+    assert_eq!(
+        filtered.reencryption_response().unverified_cfrags().len(),
+        1
+    );
+
+    // The filtered response is signed by the client
+    let verified = filtered
+        .verify(
+            &alice_sk.public_key(),
+            &client_sk.public_key(),
+            &policy_encrypting_key,
+            &bob_sk.public_key(),
+        )
+        .unwrap();
+    assert_eq!(verified.len(), 1);
+}
+
+#[wasm_bindgen_test]
+fn reencryption_response_verify_each() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
+
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule();
+    let other_capsule = MessageKit::new(&policy_encrypting_key, b"Goodbye, world!").capsule();
+
+    // The second cfrag is made for a different capsule, so it will not verify
+    let ursula_sk = SecretKey::random();
+    let mut builder = ReencryptionResponseBuilder::new(&Signer::new(&ursula_sk));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&capsule, &kfrags[0]));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&other_capsule, &kfrags[1]));
+    builder.add_capsule(&capsule);
+    builder.add_cfrag(&reencrypt(&capsule, &kfrags[2]));
+    let response = builder.build();
+
+    let capsules = [*capsule.inner(), *capsule.inner(), *capsule.inner()];
+    let results = response
+        .inner()
+        .verify_each(
+            &capsules,
+            alice_sk.public_key().inner(),
+            ursula_sk.public_key().inner(),
+            policy_encrypting_key.inner(),
+            bob_sk.public_key().inner(),
+        )
+        .ok()
+        .unwrap();
+    let valid: Vec<bool> = results.iter().map(|result| result.is_ok()).collect();
+    assert_eq!(valid, [true, false, true]);
+
+    // The all-or-nothing verification fails for the same response
+    assert!(response
+        .inner()
+        .verify(
+            &capsules,
+            alice_sk.public_key().inner(),
+            ursula_sk.public_key().inner(),
+            policy_encrypting_key.inner(),
+            bob_sk.public_key().inner(),
+        )
+        .is_err());
+
+    // A response not signed by the Ursula is rejected as a whole
+    assert!(response
+        .inner()
+        .verify_each(
+            &capsules,
+            alice_sk.public_key().inner(),
+            SecretKey::random().public_key().inner(),
+            policy_encrypting_key.inner(),
+            bob_sk.public_key().inner(),
+        )
+        .is_err());
+}
+
+#[wasm_bindgen_test]
+fn reencryption_response_unverified_cfrags() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
+
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&policy_encrypting_key, b"Hello, world!").capsule();
+    let cfrags: Vec<VerifiedCapsuleFrag> = kfrags
         .iter()
-        .map(|vkfrag| vkfrag.into_serde().unwrap())
+        .map(|kfrag| reencrypt(&capsule, kfrag))
         .collect();
 
-    assert_eq!(cfrags, verified, "Capsule fragments do not match");
+    let ursula_sk = SecretKey::random();
+    let mut builder = ReencryptionResponseBuilder::new(&Signer::new(&ursula_sk));
+    for cfrag in &cfrags {
+        builder.add_capsule(&capsule);
+        builder.add_cfrag(cfrag);
+    }
+    let reencryption_response = builder.build();
 
-    let as_bytes = reencryption_response.to_bytes();
-    assert_eq!(
-        as_bytes,
-        ReencryptionResponse::from_bytes(&as_bytes)
+    let cfrags_bytes = reencryption_response.unverified_cfrags();
+    assert_eq!(cfrags_bytes.len(), cfrags.len());
+
+    // The stored bytes can be verified again
+    for (cfrag_bytes, cfrag) in cfrags_bytes.iter().zip(cfrags.iter()) {
+        let cfrag_bytes = js_sys::Uint8Array::new(cfrag_bytes).to_vec();
+        let reverified = umbral_pre::CapsuleFrag::from_bytes(&cfrag_bytes)
             .unwrap()
-            .to_bytes(),
-        "ReencryptionResponse does not roundtrip"
-    );
+            .verify(
+                capsule.inner(),
+                alice_sk.public_key().inner(),
+                policy_encrypting_key.inner(),
+                bob_sk.public_key().inner(),
+            )
+            .unwrap();
+        assert_eq!(reverified, cfrag.inner(), "Capsule fragments do not match");
+    }
+}
+
+//
+// CfragCollector
+//
+
+#[wasm_bindgen_test]
+fn cfrag_collector() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let policy_encrypting_key = alice_sk.public_key();
+
+    // Threshold is 2 out of 3
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let message_kit = MessageKit::new(&policy_encrypting_key, b"Hello, world!");
+    let capsule = message_kit.capsule();
+
+    let mut collector = CfragCollectorBuilder::new(
+        &alice_sk.public_key(),
+        &policy_encrypting_key,
+        &bob_sk.public_key(),
+        2,
+    )
+    .add_capsule(&capsule)
+    .build();
+    assert!(!collector.is_complete());
+
+    // Each Ursula sends a response with a single cfrag
+    let ursula_sks: Vec<SecretKey> = kfrags.iter().map(|_| SecretKey::random()).collect();
+    for (i, (kfrag, ursula_sk)) in kfrags.iter().zip(ursula_sks.iter()).enumerate() {
+        let response = ReencryptionResponseBuilder::new(&Signer::new(ursula_sk))
+            .add_capsule(&capsule)
+            .add_cfrag(&reencrypt(&capsule, kfrag))
+            .build();
+        collector.add(&response, &ursula_sk.public_key()).unwrap();
+
+        assert_eq!(collector.responses_count(), i + 1);
+        assert_eq!(collector.is_complete(), i + 1 >= 2);
+    }
+
+    // A response signed by someone else is rejected
+    let response = ReencryptionResponseBuilder::new(&Signer::new(&SecretKey::random()))
+        .add_capsule(&capsule)
+        .add_cfrag(&reencrypt(&capsule, &kfrags[0]))
+        .build();
+    assert!(collector
+        .add(&response, &SecretKey::random().public_key())
+        .is_err());
+    assert_eq!(collector.responses_count(), 3);
+
+    let cfrags: Vec<VerifiedCapsuleFrag> = collector
+        .cfrags(0)
+        .unwrap()
+        .iter()
+        .map(|vcfrag| vcfrag.into_serde().unwrap())
+        .collect();
+    assert_eq!(cfrags.len(), 3);
+    assert!(collector.cfrags(1).is_err());
+}
+
+//
+// ResponseAggregator
+//
+
+#[wasm_bindgen_test]
+fn response_aggregator_unique_count() {
+    let alice_sk = SecretKey::random();
+    let bob_sk = SecretKey::random();
+    let kfrags = make_kfrags(&alice_sk, &bob_sk);
+    let capsule = MessageKit::new(&alice_sk.public_key(), b"Hello, world!").capsule();
+
+    let make_response = |ursula_sk: &SecretKey, kfrag: &VerifiedKeyFrag| {
+        ReencryptionResponseBuilder::new(&Signer::new(ursula_sk))
+            .add_capsule(&capsule)
+            .add_cfrag(&reencrypt(&capsule, kfrag))
+            .build()
+    };
+
+    let first_ursula_sk = SecretKey::random();
+    let second_ursula_sk = SecretKey::random();
+
+    let mut aggregator = ResponseAggregator::new();
+    assert!(aggregator.add(
+        &first_ursula_sk.public_key(),
+        &make_response(&first_ursula_sk, &kfrags[0])
+    ));
+    // A repeated response from the same Ursula is not counted
+    assert!(!aggregator.add(
+        &first_ursula_sk.public_key(),
+        &make_response(&first_ursula_sk, &kfrags[0])
+    ));
+    assert!(aggregator.add(
+        &second_ursula_sk.public_key(),
+        &make_response(&second_ursula_sk, &kfrags[1])
+    ));
+
+    assert_eq!(aggregator.unique_count(), 2);
+    assert_eq!(aggregator.responses().len(), 2);
 }
 
 //
@@ -492,52 +1740,393 @@ fn retrieval_kit() {
     }
     let retreival_kit = builder.build();
     assert_eq!(
-        retreival_kit.queried_addresses().unwrap().len(),
-        queried_addresses.len(),
-        "Queried addresses length does not match"
+        retreival_kit.queried_addresses().unwrap().len(),
+        queried_addresses.len(),
+        "Queried addresses length does not match"
+    );
+
+    let as_bytes = retreival_kit.to_bytes();
+    assert_eq!(
+        as_bytes,
+        RetrievalKit::from_bytes(&as_bytes).unwrap().to_bytes(),
+        "RetrievalKit does not roundtrip"
+    );
+}
+
+#[wasm_bindgen_test]
+fn retrieval_kit_builder_rejects_wrong_address() {
+    let message_kit = make_message_kit(&SecretKey::random(), b"Hello, world!");
+
+    let mut builder = RetrievalKitBuilder::new(&message_kit.capsule());
+    assert!(builder.add_queried_address(b"00000000000000000001").is_ok());
+    let err = builder
+        .add_queried_address(b"0000000000000000002")
+        .err()
+        .unwrap();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert!(message.contains("0x30303030303030303030303030303030303032"));
+    assert!(builder.add_queried_address(b"00000000000000000003").is_ok());
+
+    // The rejected address is not added
+    let retrieval_kit = builder.build();
+    assert_eq!(retrieval_kit.queried_addresses().unwrap().len(), 2);
+}
+
+#[wasm_bindgen_test]
+fn retrieval_kit_queried_addresses_hex() {
+    let message_kit = make_message_kit(&SecretKey::random(), b"Hello, world!");
+
+    let queried_addresses = [
+        b"00000000000000000001",
+        b"00000000000000000002",
+        b"00000000000000000003",
+    ];
+    let mut builder = RetrievalKitBuilder::new(&message_kit.capsule());
+    for address in queried_addresses {
+        builder.add_queried_address(address).unwrap();
+    }
+    let retrieval_kit = builder.build();
+
+    let hex_addresses: Vec<String> = retrieval_kit
+        .queried_addresses_hex()
+        .iter()
+        .map(|address| address.as_string().unwrap())
+        .collect();
+    let expected: Vec<String> = queried_addresses
+        .iter()
+        .map(|address| {
+            Address::from_slice(&address[..])
+                .unwrap()
+                .to_checksum_address()
+        })
+        .collect();
+    assert_eq!(hex_addresses, expected);
+    assert!(hex_addresses
+        .iter()
+        .all(|address| address.starts_with("0x")));
+}
+
+#[wasm_bindgen_test]
+fn multi_retrieval_kit() {
+    let sk = SecretKey::random();
+    let message_kits: Vec<MessageKit> = (0..3)
+        .map(|i| make_message_kit(&sk, format!("Hello, world {}!", i).as_bytes()))
+        .collect();
+
+    let mut builder = MultiRetrievalKitBuilder::new();
+    for message_kit in &message_kits {
+        builder.add_message_kit(message_kit);
+    }
+    builder
+        .add_queried_address(b"00000000000000000001")
+        .unwrap();
+    let multi_retrieval_kit = builder.build();
+
+    let capsules = multi_retrieval_kit.capsules();
+    assert_eq!(capsules.len(), 3);
+    assert_eq!(multi_retrieval_kit.queried_addresses().unwrap().len(), 1);
+
+    // Each capsule is present in the separate retrieval kits
+    let backend = nucypher_core::MultiRetrievalKit::from_message_kits(
+        &message_kits
+            .iter()
+            .map(|message_kit| {
+                nucypher_core::MessageKit::from_bytes(&message_kit.to_bytes()).unwrap()
+            })
+            .collect::<Vec<_>>(),
+    );
+    let retrieval_kits = backend.retrieval_kits();
+    assert_eq!(retrieval_kits.len(), 3);
+    for (retrieval_kit, message_kit) in retrieval_kits.iter().zip(message_kits.iter()) {
+        assert!(&retrieval_kit.capsule == message_kit.capsule().inner());
+        assert!(retrieval_kit.queried_addresses.is_empty());
+    }
+}
+
+//
+// RevocationOrder
+//
+
+#[wasm_bindgen_test]
+fn revocation_order() {
+    let delegating_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+
+    let hrac = make_hrac();
+    let receiving_pk = receiving_sk.public_key();
+    let signer = Signer::new(&delegating_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
+
+    let ursula_address = b"00000000000000000001";
+    let revocation_order = RevocationOrder::new(&signer, ursula_address, &encrypted_kfrag).unwrap();
+
+    assert!(revocation_order.verify(&delegating_sk.public_key()).is_ok());
+
+    let as_bytes = revocation_order.to_bytes();
+    assert_eq!(
+        as_bytes,
+        RevocationOrder::from_bytes(&as_bytes).unwrap().to_bytes(),
+        "RevocationOrder does not roundtrip"
+    );
+}
+
+#[wasm_bindgen_test]
+fn revocation_order_verified_getters() {
+    let delegating_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+
+    let signer = Signer::new(&delegating_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(
+        &signer,
+        &receiving_sk.public_key(),
+        &make_hrac(),
+        &verified_kfrags[0],
+    );
+
+    let ursula_address = b"00000000000000000001";
+    let revocation_order = RevocationOrder::new(&signer, ursula_address, &encrypted_kfrag).unwrap();
+
+    // The contents can be read back after deserialization without verification
+    let restored = RevocationOrder::from_bytes(&revocation_order.to_bytes()).unwrap();
+    assert_eq!(restored.ursula_address(), ursula_address.to_vec());
+    assert!(restored.encrypted_kfrag().equals(&encrypted_kfrag));
+
+    // or through `verify()`
+    let verified = restored.verify(&delegating_sk.public_key()).unwrap();
+    assert_eq!(verified.address().as_ref(), ursula_address);
+    assert!(verified.encrypted_kfrag().equals(&encrypted_kfrag));
+}
+
+#[wasm_bindgen_test]
+fn revocation_order_issued_at() {
+    let delegating_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+
+    let hrac = make_hrac();
+    let signer = Signer::new(&delegating_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(
+        &signer,
+        &receiving_sk.public_key(),
+        &hrac,
+        &verified_kfrags[0],
+    );
+
+    let ursula_address = b"00000000000000000001";
+    let revocation_order =
+        RevocationOrder::new_with_issued_at(&signer, ursula_address, &encrypted_kfrag, 1000)
+            .unwrap();
+    assert!(revocation_order.verify(&delegating_sk.public_key()).is_ok());
+    assert!(revocation_order.is_after(999));
+    assert!(!revocation_order.is_after(1000));
+
+    let as_bytes = revocation_order.to_bytes();
+    let revocation_order_back = RevocationOrder::from_bytes(&as_bytes).unwrap();
+    assert_eq!(revocation_order_back.issued_at(), Some(1000));
+    assert_eq!(revocation_order_back.to_bytes(), as_bytes);
+
+    // `issued_at` is serialized last, so the last byte is its least significant byte
+    let mut tampered_bytes = as_bytes.to_vec();
+    *tampered_bytes.last_mut().unwrap() ^= 1;
+    let tampered_order = RevocationOrder::from_bytes(&tampered_bytes).unwrap();
+    assert_eq!(tampered_order.issued_at(), Some(1001));
+    assert!(tampered_order.verify(&delegating_sk.public_key()).is_err());
+
+    // Orders without the epoch are never considered to be issued after anything
+    let revocation_order = RevocationOrder::new(&signer, ursula_address, &encrypted_kfrag).unwrap();
+    assert_eq!(revocation_order.issued_at(), None);
+    assert!(!revocation_order.is_after(0));
+}
+
+#[wasm_bindgen_test]
+fn revocation_order_to_chain_tuple() {
+    let delegating_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+
+    let hrac = make_hrac();
+    let signer = Signer::new(&delegating_sk);
+    let encrypted_kfrag = EncryptedKeyFrag::new(
+        &signer,
+        &receiving_sk.public_key(),
+        &hrac,
+        &verified_kfrags[0],
+    );
+
+    let ursula_address = b"00000000000000000001";
+    let revocation_order = RevocationOrder::new(&signer, ursula_address, &encrypted_kfrag).unwrap();
+    let chain_tuple = revocation_order.to_chain_tuple().unwrap();
+
+    assert_eq!(chain_tuple.address().as_ref(), ursula_address);
+    assert_eq!(
+        chain_tuple.encrypted_kfrag_hash().as_ref(),
+        Keccak256::digest(&encrypted_kfrag.to_bytes()).as_slice(),
+        "Encrypted kfrag hash does not match"
+    );
+    assert_eq!(chain_tuple.signature().len(), 64);
+
+    // The signature of an order with an issue epoch covers the epoch,
+    // which the contracts do not receive.
+    let revocation_order =
+        RevocationOrder::new_with_issued_at(&signer, ursula_address, &encrypted_kfrag, 1000)
+            .unwrap();
+    let err = revocation_order.to_chain_tuple().err().unwrap();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert_eq!(
+        message,
+        "the order has an issue epoch (1000), so its signature cannot be verified on-chain"
+    );
+}
+
+//
+// NodeMetadataPayload
+//
+
+// See below for the `NodeMetadata` struct.
+
+fn make_node_metadata_payload_with_certificate(certificate_der: &[u8]) -> NodeMetadataPayload {
+    NodeMetadataPayload::new(
+        b"00000000000000000001",
+        "localhost",
+        1546300800,
+        &SecretKey::random().public_key(),
+        &SecretKey::random().public_key(),
+        certificate_der,
+        "https://localhost.com",
+        443,
+        None,
+    )
+    .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn node_metadata_payload_to_bytes() {
+    // With the operator signature
+    let payload = make_node_metadata().payload();
+    assert!(payload.operator_signature().is_some());
+    let as_bytes = payload.to_bytes();
+    let payload_back = NodeMetadataPayload::from_bytes(&as_bytes).unwrap();
+    assert_eq!(
+        payload_back.operator_signature(),
+        payload.operator_signature()
+    );
+    assert_eq!(
+        payload_back.to_bytes(),
+        as_bytes,
+        "NodeMetadataPayload does not roundtrip"
     );
 
-    let as_bytes = retreival_kit.to_bytes();
+    // Without the operator signature
+    let payload = make_node_metadata_payload_with_certificate(b"certificate_der");
+    let as_bytes = payload.to_bytes();
+    let payload_back = NodeMetadataPayload::from_bytes(&as_bytes).unwrap();
+    assert!(payload_back.operator_signature().is_none());
     assert_eq!(
+        payload_back.to_bytes(),
         as_bytes,
-        RetrievalKit::from_bytes(&as_bytes).unwrap().to_bytes(),
-        "RetrievalKit does not roundtrip"
+        "NodeMetadataPayload does not roundtrip"
     );
 }
 
-//
-// RevocationOrder
-//
+#[wasm_bindgen_test]
+fn node_metadata_payload_builder() {
+    let verifying_key = SecretKey::random().public_key();
+    let encrypting_key = SecretKey::random().public_key();
+    let operator_signature =
+        b"0000000000000000000000000000000100000000000000000000000000000001\x00".to_vec();
+
+    let expected = NodeMetadataPayload::new(
+        b"00000000000000000001",
+        "localhost",
+        1546300800,
+        &verifying_key,
+        &encrypting_key,
+        b"certificate_der",
+        "https://localhost.com",
+        443,
+        Some(operator_signature.clone()),
+    )
+    .unwrap();
+
+    let builder = NodeMetadataPayloadBuilder::new()
+        .staking_provider_address(b"00000000000000000001")
+        .unwrap()
+        .domain("localhost")
+        .timestamp_epoch(1546300800)
+        .verifying_key(&verifying_key)
+        .encrypting_key(&encrypting_key)
+        .certificate_der(b"certificate_der")
+        .port(443)
+        .operator_signature(&operator_signature)
+        .unwrap();
+
+    // `host` is not set yet
+    let err = builder.build().err().unwrap();
+    let message = js_sys::Error::from(err).message().as_string().unwrap();
+    assert_eq!(message, "required field `host` is not set");
+
+    let payload = builder
+        .clone()
+        .host("https://localhost.com")
+        .build()
+        .unwrap();
+    assert_eq!(payload.to_bytes(), expected.to_bytes());
+}
 
 #[wasm_bindgen_test]
-fn revocation_order() {
-    let delegating_sk = SecretKey::random();
-    let receiving_sk = SecretKey::random();
-    let verified_kfrags = make_kfrags(&delegating_sk, &receiving_sk);
+fn node_metadata_payload_capabilities() {
+    let operator_signature = |payload: &NodeMetadataPayload| {
+        js_sys::Reflect::get(&payload.capabilities(), &"operatorSignature".into())
+            .unwrap()
+            .as_bool()
+            .unwrap()
+    };
 
-    let hrac = make_hrac();
-    let receiving_pk = receiving_sk.public_key();
-    let signer = Signer::new(&delegating_sk);
-    let encrypted_kfrag = EncryptedKeyFrag::new(&signer, &receiving_pk, &hrac, &verified_kfrags[0]);
+    let payload = make_node_metadata().payload();
+    assert!(operator_signature(&payload));
 
-    let ursula_address = b"00000000000000000001";
-    let revocation_order = RevocationOrder::new(&signer, ursula_address, &encrypted_kfrag).unwrap();
+    let payload = make_node_metadata_payload_with_certificate(b"certificate_der");
+    assert!(!operator_signature(&payload));
+}
 
-    assert!(revocation_order.verify(&delegating_sk.public_key()).is_ok());
+#[wasm_bindgen_test]
+fn node_metadata_payload_address_hex() {
+    let payload = make_node_metadata_payload_with_certificate(b"certificate_der");
+    let address_hex = payload.address_hex();
 
-    let as_bytes = revocation_order.to_bytes();
+    assert!(address_hex.starts_with("0x"));
+    assert_eq!(address_hex.len(), 2 + 2 * Address::SIZE);
     assert_eq!(
-        as_bytes,
-        RevocationOrder::from_bytes(&as_bytes).unwrap().to_bytes(),
-        "RevocationOrder does not roundtrip"
+        address_hex,
+        Address::from_slice(b"00000000000000000001")
+            .unwrap()
+            .to_checksum_address()
     );
 }
 
-//
-// NodeMetadataPayload
-//
+#[wasm_bindgen_test]
+fn node_metadata_payload_certificate_pem() {
+    // A DER SEQUENCE with a long-form length, long enough to span several PEM lines
+    let mut certificate_der = vec![0x30, 0x81, 0x80];
+    certificate_der.extend((0..0x80).map(|i| i as u8));
 
-// See below for the `NodeMetadata` struct.
+    let payload = make_node_metadata_payload_with_certificate(&certificate_der);
+    let pem = payload.certificate_pem().unwrap();
+
+    let lines: Vec<&str> = pem.lines().collect();
+    assert_eq!(lines[0], "-----BEGIN CERTIFICATE-----");
+    assert_eq!(lines[lines.len() - 1], "-----END CERTIFICATE-----");
+    assert!(lines.iter().all(|line| line.len() <= 64));
+
+    let decoded = base64::decode(lines[1..lines.len() - 1].concat()).unwrap();
+    assert_eq!(decoded, certificate_der);
+
+    // Not a DER certificate
+    let payload = make_node_metadata_payload_with_certificate(b"certificate_der");
+    assert!(payload.certificate_pem().is_err());
+}
 
 //
 // NodeMetadata
@@ -555,6 +2144,18 @@ fn node_metadata() {
     );
 }
 
+#[wasm_bindgen_test]
+fn node_metadata_signed_bytes() {
+    let node_metadata = make_node_metadata();
+
+    // Verify the signature independently of `NodeMetadata.verify()`
+    let signed_bytes = node_metadata.signed_bytes();
+    let backend = node_metadata.inner();
+    assert!(backend
+        .signature()
+        .verify(&backend.payload.verifying_key, &signed_bytes));
+}
+
 #[wasm_bindgen_test]
 fn node_metadata_derive_operator_address() {
     let node_metadata = make_node_metadata();
@@ -581,6 +2182,111 @@ fn fleet_state_checksum_to_bytes() {
     );
 }
 
+#[wasm_bindgen_test]
+fn fleet_state_checksum_hex() {
+    let this_node = make_node_metadata();
+    let other_nodes = vec![make_node_metadata(), make_node_metadata()];
+
+    let checksum1 = FleetStateChecksum::new(Some(&this_node), &other_nodes);
+    let checksum2 = FleetStateChecksum::new(Some(&this_node), &other_nodes);
+    assert!(checksum1.equals(&checksum2));
+    assert_eq!(checksum1.checksum(), checksum2.checksum());
+
+    let hex = checksum1.checksum();
+    assert_eq!(hex.len(), 2 * nucypher_core::FleetStateChecksum::SIZE);
+    let expected: String = checksum1
+        .to_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(hex, expected);
+
+    // Restored from a cached value
+    let cached = FleetStateChecksum::from_bytes(&checksum1.to_bytes()).unwrap();
+    assert!(cached.equals(&checksum1));
+
+    let other_checksum = FleetStateChecksum::new(None, &other_nodes);
+    assert!(!other_checksum.equals(&checksum1));
+}
+
+#[wasm_bindgen_test]
+fn fleet_state_checksum_keeps_this_node() {
+    let this_node = make_node_metadata();
+    let other_nodes = vec![make_node_metadata(), make_node_metadata()];
+
+    let mut builder = FleetStateChecksumBuilder::new();
+    builder.set_this_node(&this_node);
+    for node in &other_nodes {
+        builder.add_other_node(node);
+    }
+    let checksum = builder.build();
+
+    // The node passed to the builder is still usable
+    assert!(this_node.verify());
+    assert_eq!(
+        checksum.to_bytes(),
+        FleetStateChecksum::new(Some(&this_node), &other_nodes).to_bytes()
+    );
+}
+
+#[wasm_bindgen_test]
+fn fleet_state_checksum_hashing_backend() {
+    let node = make_node_metadata();
+    let checksum = FleetStateChecksum::new(Some(&node), &[]).to_bytes();
+
+    let sha3_checksum = Sha3_256::digest(&node.to_bytes());
+    let keccak_checksum = Keccak256::digest(&node.to_bytes());
+    assert_ne!(sha3_checksum, keccak_checksum);
+
+    #[cfg(not(feature = "keccak-fleet-state-checksum"))]
+    assert_eq!(checksum.as_ref(), sha3_checksum.as_slice());
+
+    #[cfg(feature = "keccak-fleet-state-checksum")]
+    assert_eq!(checksum.as_ref(), keccak_checksum.as_slice());
+
+    // The checksum is stable for the same nodes
+    assert_eq!(
+        FleetStateChecksum::new(Some(&node), &[]).to_bytes(),
+        checksum
+    );
+}
+
+#[wasm_bindgen_test]
+fn fleet_state_checksum_from_bytes_checks_length() {
+    let fleet_state_checksum = make_fleet_state_checksum();
+    let checksum_bytes = fleet_state_checksum.to_bytes();
+
+    let restored = FleetStateChecksum::from_bytes(&checksum_bytes).unwrap();
+    assert_eq!(restored.to_bytes(), checksum_bytes);
+
+    assert!(FleetStateChecksum::from_bytes(&checksum_bytes[1..]).is_err());
+    assert!(FleetStateChecksum::from_bytes(&[0u8; 33]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn canonical_nodes_builder() {
+    let nodes = vec![
+        make_node_metadata_with(b"00000000000000000003", 1546300800),
+        make_node_metadata_with(b"00000000000000000001", 1546300800),
+        make_node_metadata_with(b"00000000000000000002", 1546300800),
+        make_node_metadata_with(b"00000000000000000001", 1546300900),
+        make_node_metadata_with(b"00000000000000000003", 1546300700),
+    ];
+
+    let mut builder = CanonicalNodesBuilder::new();
+    for node in &nodes {
+        builder.add_node(node);
+    }
+    let canonical: Vec<NodeMetadata> = builder
+        .build()
+        .into_iter()
+        .map(|js_node| node_metadata_of_js_value(js_node).unwrap())
+        .collect();
+
+    let expected = vec![nodes[3].clone(), nodes[2].clone(), nodes[0].clone()];
+    assert_eq!(canonical, expected, "Canonical nodes do not match");
+}
+
 //
 // MetadataRequest
 //
@@ -594,7 +2300,7 @@ fn metadata_request() {
     for node in &announce_nodes {
         builder.add_announce_node(node);
     }
-    let metadata_request = builder.build();
+    let metadata_request = builder.build().unwrap();
 
     let nodes_js = metadata_request.announce_nodes();
     let nodes: Vec<NodeMetadata> = nodes_js
@@ -612,6 +2318,41 @@ fn metadata_request() {
     );
 }
 
+#[wasm_bindgen_test]
+fn metadata_request_max_nodes() {
+    let fleet_state_checksum =
+        nucypher_core::FleetStateChecksum::from_slice(&make_fleet_state_checksum().to_bytes())
+            .unwrap();
+    let node = make_node_metadata().inner().clone();
+
+    let nodes = vec![node.clone(), node.clone()];
+    assert!(
+        nucypher_core::MetadataRequest::new_with_max_nodes(&fleet_state_checksum, &nodes, 2)
+            .is_ok()
+    );
+
+    let nodes = vec![node.clone(), node.clone(), node.clone()];
+    assert_eq!(
+        nucypher_core::MetadataRequest::new_with_max_nodes(&fleet_state_checksum, &nodes, 2)
+            .unwrap_err(),
+        nucypher_core::TooManyNodesError {
+            max_nodes: 2,
+            received: 3
+        }
+    );
+
+    // An oversized request created elsewhere is rejected on deserialization
+    let nodes = vec![node; nucypher_core::MAX_ANNOUNCE_NODES + 1];
+    assert!(nucypher_core::MetadataRequest::try_new(&fleet_state_checksum, &nodes).is_err());
+    let request = nucypher_core::MetadataRequest::new_with_max_nodes(
+        &fleet_state_checksum,
+        &nodes,
+        usize::MAX,
+    )
+    .unwrap();
+    assert!(nucypher_core::MetadataRequest::from_bytes(&request.to_bytes()).is_err());
+}
+
 //
 // MetadataResponse
 //
@@ -629,6 +2370,162 @@ fn metadata_response_payload() {
     assert_eq!(nodes, announce_nodes, "Announce nodes does not match");
 }
 
+#[wasm_bindgen_test]
+fn metadata_response_payload_to_bytes() {
+    let (metadata_response_payload, announce_nodes) = make_metadata_response_payload();
+    assert_eq!(announce_nodes.len(), 2);
+
+    let as_bytes = metadata_response_payload.to_bytes();
+    let payload_back = MetadataResponsePayload::from_bytes(&as_bytes).unwrap();
+    assert_eq!(payload_back.timestamp_epoch(), 1546300800);
+
+    let nodes_bytes: Vec<Box<[u8]>> = payload_back
+        .announce_nodes()
+        .iter()
+        .cloned()
+        .map(|js_node| node_metadata_of_js_value(js_node).unwrap().to_bytes())
+        .collect();
+    let expected_bytes: Vec<Box<[u8]>> =
+        announce_nodes.iter().map(|node| node.to_bytes()).collect();
+    assert_eq!(nodes_bytes, expected_bytes, "Announce nodes do not match");
+    assert_eq!(
+        payload_back.to_bytes(),
+        as_bytes,
+        "MetadataResponsePayload does not roundtrip"
+    );
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_payload_build_canonical() {
+    let old_node = make_node_metadata_with(b"00000000000000000002", 1000);
+    let new_node = make_node_metadata_with(b"00000000000000000002", 2000);
+    let other_node = make_node_metadata_with(b"00000000000000000001", 1000);
+
+    let timestamp_epoch = 1546300800;
+    let mut builder = MetadataResponsePayloadBuilder::new(timestamp_epoch);
+    for node in [&old_node, &new_node, &other_node] {
+        builder.add_announce_node(node);
+    }
+    let payload = builder.build_canonical().unwrap();
+
+    // Duplicates are resolved in favor of the newest node, and the nodes are sorted by address.
+    let expected = nucypher_core::MetadataResponsePayload::new(
+        timestamp_epoch,
+        &[other_node.inner().clone(), new_node.inner().clone()],
+    );
+    assert_eq!(payload.to_bytes(), expected.to_bytes());
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_payload_domains() {
+    let signing_key = SecretKey::random();
+    let make_node = |address: &[u8], domain: &str| {
+        let payload = NodeMetadataPayload::new(
+            address,
+            domain,
+            1546300800,
+            &signing_key.public_key(),
+            &SecretKey::random().public_key(),
+            b"certificate_der",
+            "https://localhost.com",
+            443,
+            None,
+        )
+        .unwrap();
+        NodeMetadata::new(&Signer::new(&signing_key), &payload)
+    };
+
+    let mut builder = MetadataResponsePayloadBuilder::new(1546300800);
+    builder.add_announce_node(&make_node(b"00000000000000000001", "mainnet"));
+    builder.add_announce_node(&make_node(b"00000000000000000002", "lynx"));
+    builder.add_announce_node(&make_node(b"00000000000000000003", "mainnet"));
+    let payload = builder.build().unwrap();
+
+    let domains: Vec<String> = payload
+        .domains()
+        .iter()
+        .map(|domain| domain.as_string().unwrap())
+        .collect();
+    assert_eq!(domains, ["lynx", "mainnet"]);
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_payload_is_fresher_than() {
+    let node = make_node_metadata();
+    let make_payload = |timestamp_epoch| {
+        MetadataResponsePayloadBuilder::new(timestamp_epoch)
+            .add_announce_node(&node)
+            .build()
+            .unwrap()
+    };
+
+    let older = make_payload(1546300800);
+    let newer = make_payload(1546300900);
+    assert!(newer.is_fresher_than(&older));
+    assert!(!older.is_fresher_than(&newer));
+
+    let same = make_payload(1546300800);
+    assert!(!same.is_fresher_than(&older));
+    assert!(!older.is_fresher_than(&same));
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_payload_canonical_bytes() {
+    let node1 = make_node_metadata_with(b"00000000000000000001", 1546300800)
+        .inner()
+        .clone();
+    let node2 = make_node_metadata_with(b"00000000000000000002", 1546300800)
+        .inner()
+        .clone();
+
+    let payload1 =
+        nucypher_core::MetadataResponsePayload::new(1546300800, &[node1.clone(), node2.clone()]);
+    let payload2 = nucypher_core::MetadataResponsePayload::new(1546300801, &[node2, node1]);
+
+    assert_eq!(payload1.canonical_bytes(), payload2.canonical_bytes());
+
+    // A different set of nodes gives different bytes
+    let payload3 = nucypher_core::MetadataResponsePayload::new(
+        1546300800,
+        &[make_node_metadata().inner().clone()],
+    );
+    assert_ne!(payload1.canonical_bytes(), payload3.canonical_bytes());
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_payload_max_nodes() {
+    let node = make_node_metadata().inner().clone();
+    let timestamp_epoch = 1546300800;
+
+    let nodes = vec![node.clone(), node.clone()];
+    assert!(
+        nucypher_core::MetadataResponsePayload::new_with_max_nodes(timestamp_epoch, &nodes, 2)
+            .is_ok()
+    );
+
+    let nodes = vec![node.clone(), node.clone(), node.clone()];
+    assert_eq!(
+        nucypher_core::MetadataResponsePayload::new_with_max_nodes(timestamp_epoch, &nodes, 2)
+            .unwrap_err(),
+        nucypher_core::TooManyNodesError {
+            max_nodes: 2,
+            received: 3
+        }
+    );
+
+    // An oversized response created elsewhere is rejected on deserialization
+    let nodes = vec![node; nucypher_core::MAX_ANNOUNCE_NODES + 1];
+    let payload = nucypher_core::MetadataResponsePayload::new_with_max_nodes(
+        timestamp_epoch,
+        &nodes,
+        usize::MAX,
+    )
+    .unwrap();
+    let signer = Signer::new(&SecretKey::random());
+    let response = nucypher_core::MetadataResponse::new(signer.inner(), &payload);
+    assert!(nucypher_core::MetadataResponse::from_bytes(&response.to_bytes()).is_err());
+}
+
 #[wasm_bindgen_test]
 fn metadata_response() {
     let (metadata_response_payload, _) = make_metadata_response_payload();
@@ -644,3 +2541,258 @@ fn metadata_response() {
         "MetadataResponse does not roundtrip"
     );
 }
+
+#[wasm_bindgen_test]
+fn metadata_response_split_by_size() {
+    let announce_nodes: Vec<NodeMetadata> = (1..=5)
+        .map(|i| {
+            make_node_metadata_with(format!("0000000000000000000{}", i).as_bytes(), 1546300800)
+        })
+        .collect();
+    let mut payload_builder = MetadataResponsePayloadBuilder::new(1546300800);
+    for node in &announce_nodes {
+        payload_builder.add_announce_node(node);
+    }
+    let payload = payload_builder.build().unwrap();
+
+    let ursula_sk = SecretKey::random();
+    let signer = Signer::new(&ursula_sk);
+    let response = MetadataResponse::new(&signer, &payload);
+
+    // Enough for two nodes, but not for three
+    let node_len = announce_nodes[0].to_bytes().len() - HEADER_SIZE;
+    let empty_len = MetadataResponse::new(
+        &signer,
+        &MetadataResponsePayloadBuilder::new(1546300800)
+            .build()
+            .unwrap(),
+    )
+    .to_bytes()
+    .len();
+    let max_bytes = empty_len + 2 * node_len + 1;
+
+    let chunks: Vec<MetadataResponse> = response
+        .split_by_size(&signer, max_bytes)
+        .unwrap()
+        .into_iter()
+        .map(|js_chunk| metadata_response_of_js_value(js_chunk).unwrap())
+        .collect();
+    assert_eq!(chunks.len(), 3);
+
+    let mut restored_nodes = Vec::new();
+    for chunk in &chunks {
+        assert!(chunk.to_bytes().len() <= max_bytes);
+        let chunk_payload = chunk.verify(&ursula_sk.public_key()).unwrap();
+        assert_eq!(chunk_payload.timestamp_epoch(), 1546300800);
+        restored_nodes.extend(
+            chunk_payload
+                .announce_nodes()
+                .into_iter()
+                .map(|js_node| node_metadata_of_js_value(js_node).unwrap()),
+        );
+    }
+    assert_eq!(restored_nodes, announce_nodes);
+
+    // A limit too small for a single node
+    assert!(response.split_by_size(&signer, empty_len + 1).is_err());
+}
+
+#[wasm_bindgen_test]
+fn metadata_response_signed_bytes() {
+    let (metadata_response_payload, _) = make_metadata_response_payload();
+    let signing_sk = SecretKey::random();
+    let metadata_response =
+        MetadataResponse::new(&Signer::new(&signing_sk), &metadata_response_payload);
+
+    // Verify the signature independently of `MetadataResponse.verify()`
+    let signature = umbral_pre::Signature::from_bytes(metadata_response.signature()).unwrap();
+    let signed_bytes = metadata_response.signed_bytes();
+    assert!(signature.verify(signing_sk.public_key().inner(), &signed_bytes));
+}
+
+//
+// SerializationBuffer
+//
+
+#[wasm_bindgen_test]
+fn serialization_buffer_reuse() {
+    let mut buffer = SerializationBuffer::new(1024);
+    let target = Uint8Array::new_with_length(4096);
+
+    let mut serialized: Vec<(usize, Box<[u8]>)> = Vec::new();
+    for i in 0..10 {
+        let message_kit = make_message_kit(&SecretKey::random(), &vec![i as u8; i * 10]);
+        serialized.push((
+            message_kit.to_bytes_into(&mut buffer),
+            message_kit.to_bytes(),
+        ));
+
+        let node_metadata = make_node_metadata();
+        serialized.push((
+            node_metadata.to_bytes_into(&mut buffer),
+            node_metadata.to_bytes(),
+        ));
+
+        // The buffer only holds the last serialized object
+        let (len, expected) = serialized.last().unwrap();
+        assert_eq!(buffer.length(), *len);
+        assert_eq!(buffer.copy_into(&target).unwrap(), *len);
+        assert_eq!(target.subarray(0, *len as u32).to_vec(), expected.to_vec());
+    }
+
+    for (len, expected) in serialized {
+        assert_eq!(len, expected.len());
+    }
+
+    // `TreasureMapArchive` has a custom serialization format
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let archive = TreasureMapArchiveBuilder::new()
+        .add_treasure_map(&make_treasure_map(&publisher_sk, &receiving_sk))
+        .build();
+    let len = archive.to_bytes_into(&mut buffer);
+    assert_eq!(buffer.copy_into(&target).unwrap(), len);
+    assert_eq!(
+        target.subarray(0, len as u32).to_vec(),
+        archive.to_bytes().to_vec()
+    );
+
+    // The target array must be large enough
+    assert!(buffer.copy_into(&Uint8Array::new_with_length(1)).is_err());
+}
+
+//
+// Object brands
+//
+
+#[wasm_bindgen_test]
+fn equals() {
+    let sk = SecretKey::random();
+    let message_kit = make_message_kit(&sk, b"Hello, world!");
+    assert!(message_kit.equals(&message_kit.duplicate()));
+    assert!(message_kit.equals(&MessageKit::from_bytes(&message_kit.to_bytes()).unwrap()));
+    assert!(!message_kit.equals(&make_message_kit(&sk, b"Hello, world!")));
+
+    let publisher_sk = SecretKey::random();
+    let receiving_sk = SecretKey::random();
+    let signer = Signer::new(&publisher_sk);
+    let treasure_map = make_treasure_map(&publisher_sk, &receiving_sk);
+    assert!(treasure_map.equals(&TreasureMap::from_bytes(&treasure_map.to_bytes()).unwrap()));
+    assert!(!treasure_map.equals(&make_treasure_map(&publisher_sk, &receiving_sk)));
+
+    let encrypted_map = treasure_map.encrypt(&signer, &receiving_sk.public_key());
+    assert!(encrypted_map.equals(&encrypted_map.duplicate()));
+    assert!(!encrypted_map.equals(&treasure_map.encrypt(&signer, &receiving_sk.public_key())));
+
+    let vkfrags = make_kfrags(&publisher_sk, &receiving_sk);
+    let hrac = make_hrac();
+    let ekfrag = EncryptedKeyFrag::new(&signer, &receiving_sk.public_key(), &hrac, &vkfrags[0]);
+    assert!(ekfrag.equals(&EncryptedKeyFrag::from_bytes(&ekfrag.to_bytes()).unwrap()));
+    assert!(!ekfrag.equals(&EncryptedKeyFrag::new(
+        &signer,
+        &receiving_sk.public_key(),
+        &hrac,
+        &vkfrags[0]
+    )));
+
+    let node_metadata = make_node_metadata();
+    assert!(node_metadata.equals(&NodeMetadata::from_bytes(&node_metadata.to_bytes()).unwrap()));
+    assert!(!node_metadata.equals(&make_node_metadata_with(
+        b"00000000000000000002",
+        1546300800
+    )));
+}
+
+#[wasm_bindgen_test]
+fn brand_of_serialized_objects() {
+    let message_kit = MessageKit::new(&SecretKey::random().public_key(), b"Hello, world!");
+    assert_eq!(brand_of(&message_kit.to_bytes()).unwrap(), "MessageKit");
+
+    let node_metadata = make_node_metadata();
+    assert_eq!(brand_of(&node_metadata.to_bytes()).unwrap(), "NodeMetadata");
+
+    assert_eq!(
+        ObjectBrand::MessageKit as u32,
+        u32::from_be_bytes(*b"MKit"),
+        "Brand value does not match the serialized brand"
+    );
+
+    assert!(brand_of(b"MK").is_err());
+    assert!(brand_of(b"XXXX0000").is_err());
+}
+
+#[wasm_bindgen_test]
+fn split_concatenated_objects() {
+    let message_kit = MessageKit::new(&SecretKey::random().public_key(), b"Hello, world!");
+    let node_metadata = make_node_metadata();
+
+    let message_kit_bytes = message_kit.to_bytes();
+    let node_metadata_bytes = node_metadata.to_bytes();
+    let data = [message_kit_bytes.as_ref(), node_metadata_bytes.as_ref()].concat();
+
+    let objects = split_objects(&data).unwrap();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[0].0, b"MKit");
+    assert_eq!(objects[0].1, message_kit_bytes.as_ref());
+    assert_eq!(objects[1].0, b"NdMd");
+    assert_eq!(objects[1].1, node_metadata_bytes.as_ref());
+
+    assert_eq!(MessageKit::from_bytes(objects[0].1).unwrap(), message_kit);
+
+    // A truncated object is an error
+    assert!(split_objects(&data[..data.len() - 1]).is_err());
+
+    // Huge declared lengths (map 32 with 2^32 - 1 entries, bin 32 with 2^32 - 1 bytes)
+    // are an error too, and not an overflow
+    for payload in [
+        [0xdf, 0xff, 0xff, 0xff, 0xff],
+        [0xc6, 0xff, 0xff, 0xff, 0xff],
+    ] {
+        let object = [b"MKit".as_ref(), &[0, 1, 0, 0], &payload].concat();
+        assert!(split_objects(&object).is_err());
+    }
+}
+
+#[wasm_bindgen_test]
+fn try_decode_any_never_panics() {
+    let message_kit = MessageKit::new(&SecretKey::random().public_key(), b"Hello, world!");
+    let node_metadata = make_node_metadata();
+
+    match try_decode_any(&message_kit.to_bytes()).unwrap() {
+        DecodedObject::MessageKit(decoded) => {
+            assert_eq!(decoded.to_bytes(), message_kit.to_bytes())
+        }
+        _ => panic!("Decoded a wrong object type"),
+    }
+    match try_decode_any(&node_metadata.to_bytes()).unwrap() {
+        DecodedObject::NodeMetadata(decoded) => assert_eq!(&decoded, node_metadata.inner()),
+        _ => panic!("Decoded a wrong object type"),
+    }
+
+    // A simple xorshift generator, so that the test is reproducible
+    let mut state: u32 = 0x1234_5678;
+    let mut next_byte = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 24) as u8
+    };
+
+    let valid_objects = [message_kit.to_bytes(), node_metadata.to_bytes()];
+    for i in 0..500 {
+        // Random bytes, either entirely random or with a valid header
+        let len = (next_byte() as usize) % 200;
+        let mut data: Vec<u8> = (0..len).map(|_| next_byte()).collect();
+        if i % 2 == 0 && data.len() >= 8 {
+            data[..8].copy_from_slice(&valid_objects[i % 4 / 2][..8]);
+        }
+        let _result = try_decode_any(&data);
+
+        // Valid objects with random corruption or truncation
+        let mut data = valid_objects[i % 2].to_vec();
+        let position = (next_byte() as usize * 256 + next_byte() as usize) % data.len();
+        data[position] = next_byte();
+        let _result = try_decode_any(&data);
+        let _result = try_decode_any(&data[..position]);
+    }
+}
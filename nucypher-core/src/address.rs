@@ -1,3 +1,6 @@
+use alloc::format;
+use alloc::string::String;
+
 use generic_array::sequence::Split;
 use generic_array::GenericArray;
 use k256::elliptic_curve::sec1::ToEncodedPoint;
@@ -7,11 +10,13 @@ use sha3::{Digest, Keccak256};
 use typenum::{U12, U20};
 
 use crate::arrays_as_bytes;
+use crate::LengthMismatchError;
 
 // We could use the third-party `ethereum_types::Address` here,
 // but it has an inefficient `serde` implementation (serializes as hex instead of bytes).
 // So for simplicity we just use our own type since we only need the size check.
-// Later a conversion method can be easily defined to/from `ethereum_types::Address`.
+// Conversions to/from `ethereum_types::Address` (that is, `H160`) are available
+// with the `ethereum-types` feature.
 
 /// Represents an Ethereum address (20 bytes).
 #[derive(PartialEq, Debug, Serialize, Deserialize, Copy, Clone, PartialOrd, Eq, Ord)]
@@ -26,6 +31,17 @@ impl Address {
         Self(*bytes)
     }
 
+    /// Creates an address from a slice, checking its length.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, LengthMismatchError> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_err| LengthMismatchError {
+                expected: Self::SIZE,
+                received: bytes.len(),
+            })
+    }
+
     pub(crate) fn from_k256_public_key(pk: &impl ToEncodedPoint<Secp256k1>) -> Self {
         // Canonical address is the last 20 bytes of keccak256 hash
         // of the uncompressed public key (without the header, so 64 bytes in total).
@@ -37,6 +53,29 @@ impl Address {
 
         Self(address.into())
     }
+
+    /// Returns the hex representation of the address with the `0x` prefix
+    /// and the EIP-55 mixed-case checksum.
+    pub fn to_checksum_address(&self) -> String {
+        let hex: String = self.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let digest = Keccak256::digest(hex.as_bytes());
+
+        let mut result = String::from("0x");
+        for (i, c) in hex.chars().enumerate() {
+            // Each character corresponds to a nibble of the hash of the lowercase hex string.
+            let nibble = if i % 2 == 0 {
+                digest[i / 2] >> 4
+            } else {
+                digest[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
 }
 
 impl AsRef<[u8]> for Address {
@@ -45,8 +84,31 @@ impl AsRef<[u8]> for Address {
     }
 }
 
+impl TryFrom<&[u8]> for Address {
+    type Error = LengthMismatchError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_slice(bytes)
+    }
+}
+
 impl From<Address> for [u8; Address::SIZE] {
     fn from(address: Address) -> [u8; Address::SIZE] {
         address.0
     }
 }
+
+#[cfg(feature = "ethereum-types")]
+impl From<ethereum_types::H160> for Address {
+    fn from(address: ethereum_types::H160) -> Self {
+        // `H160` is always 20 bytes long, so no length check is needed.
+        Self(address.to_fixed_bytes())
+    }
+}
+
+#[cfg(feature = "ethereum-types")]
+impl From<Address> for ethereum_types::H160 {
+    fn from(address: Address) -> Self {
+        Self::from(address.0)
+    }
+}
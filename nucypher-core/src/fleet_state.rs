@@ -1,15 +1,36 @@
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Sha3_256};
+use sha3::Digest;
 
 use crate::arrays_as_bytes;
 use crate::node_metadata::NodeMetadata;
 use crate::versioning::ProtocolObject;
+use crate::LengthMismatchError;
+
+// The checksum is compared between nodes, so all of them must use the same hash.
+// SHA3-256 is used by the reference Python nodes and the published bindings;
+// Keccak-256 can be selected for the deployments standardized on it
+// (e.g. to match the hashes computed in Ethereum contracts).
+#[cfg(not(feature = "keccak-fleet-state-checksum"))]
+type ChecksumDigest = sha3::Sha3_256;
+
+#[cfg(feature = "keccak-fleet-state-checksum")]
+type ChecksumDigest = sha3::Keccak256;
 
 /// An identifier of the fleet state.
+///
+/// It is a SHA3-256 hash of the node metadata, or a Keccak-256 hash
+/// if the `keccak-fleet-state-checksum` feature is enabled.
+/// Checksums created with different hashes do not match,
+/// so the nodes comparing them must be built with the same choice.
 #[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct FleetStateChecksum(#[serde(with = "arrays_as_bytes")] [u8; 32]);
+pub struct FleetStateChecksum(#[serde(with = "arrays_as_bytes")] [u8; FleetStateChecksum::SIZE]);
 
 impl FleetStateChecksum {
+    /// The size of the checksum in bytes.
+    pub const SIZE: usize = 32;
+
     /// Creates a checksum from the given list of node metadata, and, possibly,
     /// also the metadata of the requesting node.
     pub fn from_nodes(this_node: Option<&NodeMetadata>, other_nodes: &[NodeMetadata]) -> Self {
@@ -30,7 +51,7 @@ impl FleetStateChecksum {
 
         let checksum = nodes
             .iter()
-            .fold(Sha3_256::new(), |digest, node| {
+            .fold(ChecksumDigest::new(), |digest, node| {
                 // NodeMetadata has a payload signature, which is randomized,
                 // so this may lead to unnecessary fleet state update.
                 // But, unlike ProtocolObject::to_bytes(), payload serialization
@@ -41,6 +62,17 @@ impl FleetStateChecksum {
 
         Self(checksum.into())
     }
+
+    /// Creates a checksum from a slice, checking its length.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, LengthMismatchError> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_err| LengthMismatchError {
+                expected: Self::SIZE,
+                received: bytes.len(),
+            })
+    }
 }
 
 impl AsRef<[u8]> for FleetStateChecksum {
@@ -48,3 +80,36 @@ impl AsRef<[u8]> for FleetStateChecksum {
         self.0.as_ref()
     }
 }
+
+impl TryFrom<&[u8]> for FleetStateChecksum {
+    type Error = LengthMismatchError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_slice(bytes)
+    }
+}
+
+/// Sorts the nodes by the staking provider address, and removes the nodes with repeating addresses,
+/// keeping the one with the latest timestamp.
+pub fn canonicalize_nodes(nodes: Vec<NodeMetadata>) -> Vec<NodeMetadata> {
+    let mut nodes = nodes;
+
+    // Within the same address, the newest node goes first, so that it is the one kept by `dedup`.
+    nodes.sort_by(|node1, node2| {
+        node1
+            .payload
+            .staking_provider_address
+            .cmp(&node2.payload.staking_provider_address)
+            .then(
+                node2
+                    .payload
+                    .timestamp_epoch
+                    .cmp(&node1.payload.timestamp_epoch),
+            )
+    });
+    nodes.dedup_by(|node, prev_node| {
+        node.payload.staking_provider_address == prev_node.payload.staking_provider_address
+    });
+
+    nodes
+}
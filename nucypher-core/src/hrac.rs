@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 use generic_array::sequence::Split;
 use generic_array::GenericArray;
 use serde::{Deserialize, Serialize};
@@ -6,6 +8,7 @@ use typenum::U16;
 use umbral_pre::{PublicKey, SerializableToArray};
 
 use crate::arrays_as_bytes;
+use crate::LengthMismatchError;
 
 /// "hashed resource access code".
 ///
@@ -17,7 +20,7 @@ use crate::arrays_as_bytes;
 /// Publisher and Bob have all the information they need to construct this.
 /// Ursula does not, so we share it with her.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct HRAC(#[serde(with = "arrays_as_bytes")] [u8; HRAC::SIZE]);
 
 impl HRAC {
@@ -40,6 +43,17 @@ impl HRAC {
         let (hrac, _rest): (GenericArray<u8, U16>, GenericArray<u8, _>) = digest.split();
         Self(hrac.into())
     }
+
+    /// Creates an HRAC from a slice, checking its length.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, LengthMismatchError> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_err| LengthMismatchError {
+                expected: Self::SIZE,
+                received: bytes.len(),
+            })
+    }
 }
 
 impl From<[u8; HRAC::SIZE]> for HRAC {
@@ -48,8 +62,34 @@ impl From<[u8; HRAC::SIZE]> for HRAC {
     }
 }
 
+impl TryFrom<&[u8]> for HRAC {
+    type Error = LengthMismatchError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_slice(bytes)
+    }
+}
+
 impl AsRef<[u8]> for HRAC {
     fn as_ref(&self) -> &[u8] {
         self.0.as_ref()
     }
 }
+
+/// Implemented by objects that can reference a policy via its HRAC.
+pub trait HasHrac {
+    /// Returns the HRAC of the policy this object refers to,
+    /// or `None` if the object does not carry it in the open.
+    fn referenced_hrac(&self) -> Option<HRAC>;
+}
+
+/// Returns the distinct HRACs referenced by `objects`, in the order of first appearance.
+pub fn collect_hracs(objects: &[&dyn HasHrac]) -> Vec<HRAC> {
+    let mut hracs = Vec::new();
+    for hrac in objects.iter().filter_map(|object| object.referenced_hrac()) {
+        if !hracs.contains(&hrac) {
+            hracs.push(hrac);
+        }
+    }
+    hracs
+}
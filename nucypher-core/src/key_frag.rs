@@ -10,8 +10,8 @@ use umbral_pre::{
 
 use crate::hrac::HRAC;
 use crate::versioning::{
-    messagepack_deserialize, messagepack_serialize, DeserializationError, ProtocolObject,
-    ProtocolObjectInner,
+    messagepack_bytes_len, messagepack_deserialize, messagepack_serialize, DeserializationError,
+    ProtocolObject, ProtocolObjectInner, HEADER_SIZE,
 };
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +137,16 @@ impl EncryptedKeyFrag {
             .verify(hrac, publisher_verifying_key)
             .ok_or(DecryptionError::VerificationFailed)
     }
+
+    /// Returns the length of the serialized object (the same as `to_bytes().len()`),
+    /// without actually serializing it.
+    pub fn serialized_len(&self) -> usize {
+        // The struct is serialized as a 2-element array, which takes one byte for the prefix.
+        HEADER_SIZE
+            + 1
+            + messagepack_bytes_len(self.capsule.to_array().len())
+            + messagepack_bytes_len(self.ciphertext.len())
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for EncryptedKeyFrag {
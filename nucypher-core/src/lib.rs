@@ -7,6 +7,8 @@
 
 extern crate alloc;
 
+use core::fmt;
+
 mod address;
 mod arrays_as_bytes;
 mod fleet_state;
@@ -23,20 +25,48 @@ mod versioning;
 /// Error returned by various `verify()` methods in the crate.
 pub struct VerificationError;
 
+/// Error returned when creating a fixed-size object from a slice of a wrong length.
+#[derive(Debug, PartialEq)]
+pub struct LengthMismatchError {
+    /// The expected length, in bytes.
+    pub expected: usize,
+    /// The length of the given slice.
+    pub received: usize,
+}
+
+impl fmt::Display for LengthMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "incorrect length: expected {} bytes, got {}",
+            self.expected, self.received
+        )
+    }
+}
+
 pub use address::Address;
-pub use fleet_state::FleetStateChecksum;
-pub use hrac::HRAC;
+pub use fleet_state::{canonicalize_nodes, FleetStateChecksum};
+pub use hrac::{collect_hracs, HasHrac, HRAC};
 pub use key_frag::EncryptedKeyFrag;
-pub use message_kit::MessageKit;
+pub use message_kit::{DecryptReencryptedError, EmptyPlaintextError, MessageKit};
 pub use node_metadata::{
-    MetadataRequest, MetadataResponse, MetadataResponsePayload, NodeMetadata, NodeMetadataPayload,
+    Capabilities, MetadataRequest, MetadataResponse, MetadataResponsePayload,
+    MetadataResponsePayloadBuilder, MissingFieldError, NodeMetadata, NodeMetadataPayload,
+    NodeMetadataPayloadBuilder, NodeTooLargeError, TooManyNodesError, MAX_ANNOUNCE_NODES,
     RECOVERABLE_SIGNATURE_SIZE,
 };
-pub use reencryption::{ReencryptionRequest, ReencryptionResponse};
-pub use retrieval_kit::RetrievalKit;
-pub use revocation_order::RevocationOrder;
-pub use treasure_map::{EncryptedTreasureMap, TreasureMap};
-pub use versioning::ProtocolObject;
+pub use reencryption::{
+    CfragBytesError, CfragCollector, ReencryptionRequest, ReencryptionResponse, ResponseAggregator,
+};
+pub use retrieval_kit::{MultiRetrievalKit, RetrievalKit};
+pub use revocation_order::{ChainTupleError, RevocationOrder};
+pub use treasure_map::{
+    EncryptedTreasureMap, TreasureMap, TreasureMapArchive, TreasureMapStore, ValidationError,
+};
+pub use versioning::{
+    split_objects, try_decode_any, DecodedObject, DeserializationError, ProtocolObject,
+    VersionObserver, HEADER_SIZE,
+};
 
 // Re-export umbral_pre so that the users don't have to version-match.
 pub use k256;
@@ -1,5 +1,7 @@
 use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 
 use serde::{Deserialize, Serialize};
 use umbral_pre::{
@@ -7,10 +9,48 @@ use umbral_pre::{
     PublicKey, ReencryptionError, SecretKey, VerifiedCapsuleFrag,
 };
 
+use crate::retrieval_kit::RetrievalKit;
 use crate::versioning::{
     messagepack_deserialize, messagepack_serialize, ProtocolObject, ProtocolObjectInner,
 };
 
+/// Errors that can happen when decrypting a message with re-encrypted capsule frags.
+#[derive(Debug)]
+pub enum DecryptReencryptedError {
+    /// The number of capsule frags is less than the policy threshold.
+    InsufficientCapsuleFrags {
+        /// The number of provided capsule frags.
+        got: usize,
+        /// The number of capsule frags required by the policy.
+        needed: usize,
+    },
+    /// The underlying decryption failed.
+    DecryptionFailed(ReencryptionError),
+}
+
+impl fmt::Display for DecryptReencryptedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InsufficientCapsuleFrags { got, needed } => write!(
+                f,
+                "insufficient capsule frags: got {}, need at least {}",
+                got, needed
+            ),
+            Self::DecryptionFailed(err) => write!(f, "decryption failed: {}", err),
+        }
+    }
+}
+
+/// Error returned by [`MessageKit::new_non_empty`] if the plaintext is empty.
+#[derive(Debug, PartialEq)]
+pub struct EmptyPlaintextError;
+
+impl fmt::Display for EmptyPlaintextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "plaintext is empty")
+    }
+}
+
 /// Encrypted message prepared for re-encryption.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct MessageKit {
@@ -37,6 +77,35 @@ impl MessageKit {
         }
     }
 
+    /// Same as [`new`](`Self::new`), but fails if `plaintext` is empty
+    /// (which is allowed by `new`, but may indicate a bug on the caller's side).
+    pub fn new_non_empty(
+        policy_encrypting_key: &PublicKey,
+        plaintext: &[u8],
+    ) -> Result<Self, EmptyPlaintextError> {
+        if plaintext.is_empty() {
+            return Err(EmptyPlaintextError);
+        }
+        Ok(Self::new(policy_encrypting_key, plaintext))
+    }
+
+    /// Recreates a message kit from the capsule stored in a retrieval kit
+    /// and the ciphertext it was created with.
+    ///
+    /// The correspondence of the ciphertext to the capsule is not checked here;
+    /// a mismatch will lead to a decryption error.
+    pub fn from_retrieval_kit(retrieval_kit: &RetrievalKit, ciphertext: &[u8]) -> Self {
+        Self {
+            capsule: retrieval_kit.capsule,
+            ciphertext: ciphertext.into(),
+        }
+    }
+
+    /// Returns the encrypted message.
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
     /// Decrypts the message using the original (Alice's) key.
     pub fn decrypt(&self, sk: &SecretKey) -> Result<Box<[u8]>, DecryptionError> {
         decrypt_original(sk, &self.capsule, &self.ciphertext)
@@ -57,6 +126,26 @@ impl MessageKit {
             self.ciphertext.clone(),
         )
     }
+
+    /// Same as [`decrypt_reencrypted`](`Self::decrypt_reencrypted`),
+    /// but checks beforehand that there are at least `threshold` capsule frags.
+    pub fn decrypt_reencrypted_with_threshold(
+        &self,
+        sk: &SecretKey,
+        policy_encrypting_key: &PublicKey,
+        cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+        threshold: u8,
+    ) -> Result<Box<[u8]>, DecryptReencryptedError> {
+        let cfrags: Vec<_> = cfrags.into_iter().collect();
+        if cfrags.len() < threshold as usize {
+            return Err(DecryptReencryptedError::InsufficientCapsuleFrags {
+                got: cfrags.len(),
+                needed: threshold as usize,
+            });
+        }
+        self.decrypt_reencrypted(sk, policy_encrypting_key, cfrags)
+            .map_err(DecryptReencryptedError::DecryptionFailed)
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for MessageKit {
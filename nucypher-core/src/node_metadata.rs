@@ -1,6 +1,9 @@
 use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
+use alloc::vec::Vec;
 use core::fmt;
 
 use k256::ecdsa::recoverable;
@@ -11,9 +14,10 @@ use umbral_pre::{PublicKey, SerializableToArray, Signature, Signer};
 
 use crate::address::Address;
 use crate::arrays_as_bytes::{self, DeserializeAsBytes, SerializeAsBytes};
-use crate::fleet_state::FleetStateChecksum;
+use crate::fleet_state::{canonicalize_nodes, FleetStateChecksum};
 use crate::versioning::{
-    messagepack_deserialize, messagepack_serialize, ProtocolObject, ProtocolObjectInner,
+    messagepack_array_header_len, messagepack_deserialize, messagepack_serialize, ProtocolObject,
+    ProtocolObjectInner,
 };
 use crate::VerificationError;
 
@@ -113,7 +117,9 @@ pub struct NodeMetadataPayload {
 
 impl NodeMetadataPayload {
     // Standard payload serialization for signing purposes.
-    fn to_bytes(&self) -> Box<[u8]> {
+    // Same as `unversioned_to_bytes()`, that is without the header,
+    // so that the signatures do not depend on the payload version.
+    fn signed_bytes(&self) -> Box<[u8]> {
         messagepack_serialize(self)
     }
 
@@ -129,6 +135,138 @@ impl NodeMetadataPayload {
             .map_err(AddressDerivationError::RecoveryFailed)?;
         Ok(Address::from_k256_public_key(&key))
     }
+
+    /// Reports which of the optional fields are present in the payload.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            operator_signature: self.operator_signature.is_some(),
+        }
+    }
+}
+
+/// The optional features a node advertises in its [`NodeMetadataPayload`].
+///
+/// More flags may be added as the payload gains optional fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// `true` if the payload contains the operator signature.
+    pub operator_signature: bool,
+}
+
+/// Indicates that a required field was not set in [`NodeMetadataPayloadBuilder`].
+#[derive(Debug, PartialEq)]
+pub struct MissingFieldError {
+    /// The name of the missing field.
+    pub field: &'static str,
+}
+
+impl fmt::Display for MissingFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "required field `{}` is not set", self.field)
+    }
+}
+
+/// A builder for [`NodeMetadataPayload`], an alternative to filling in all the fields at once.
+///
+/// All the fields except `operator_signature` are required.
+#[derive(Debug, Clone, Default)]
+pub struct NodeMetadataPayloadBuilder {
+    staking_provider_address: Option<Address>,
+    domain: Option<String>,
+    timestamp_epoch: Option<u32>,
+    verifying_key: Option<PublicKey>,
+    encrypting_key: Option<PublicKey>,
+    certificate_der: Option<Box<[u8]>>,
+    host: Option<String>,
+    port: Option<u16>,
+    operator_signature: Option<recoverable::Signature>,
+}
+
+impl NodeMetadataPayloadBuilder {
+    /// Creates a builder with no fields set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the staking provider's Ethereum address.
+    pub fn staking_provider_address(mut self, address: &Address) -> Self {
+        self.staking_provider_address = Some(*address);
+        self
+    }
+
+    /// Sets the network identifier.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Sets the timestamp of the metadata creation.
+    pub fn timestamp_epoch(mut self, timestamp_epoch: u32) -> Self {
+        self.timestamp_epoch = Some(timestamp_epoch);
+        self
+    }
+
+    /// Sets the node's verifying key.
+    pub fn verifying_key(mut self, verifying_key: &PublicKey) -> Self {
+        self.verifying_key = Some(*verifying_key);
+        self
+    }
+
+    /// Sets the node's encrypting key.
+    pub fn encrypting_key(mut self, encrypting_key: &PublicKey) -> Self {
+        self.encrypting_key = Some(*encrypting_key);
+        self
+    }
+
+    /// Sets the node's SSL certificate (serialized in DER format).
+    pub fn certificate_der(mut self, certificate_der: &[u8]) -> Self {
+        self.certificate_der = Some(certificate_der.into());
+        self
+    }
+
+    /// Sets the hostname of the node's REST service.
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Sets the port of the node's REST service.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets the operator signature of the node's verifying key.
+    pub fn operator_signature(mut self, operator_signature: &recoverable::Signature) -> Self {
+        self.operator_signature = Some(*operator_signature);
+        self
+    }
+
+    /// Creates the payload, failing if any of the required fields is not set.
+    pub fn build(&self) -> Result<NodeMetadataPayload, MissingFieldError> {
+        fn required<T: Clone>(
+            value: &Option<T>,
+            field: &'static str,
+        ) -> Result<T, MissingFieldError> {
+            value.clone().ok_or(MissingFieldError { field })
+        }
+
+        Ok(NodeMetadataPayload {
+            staking_provider_address: required(
+                &self.staking_provider_address,
+                "staking_provider_address",
+            )?,
+            domain: required(&self.domain, "domain")?,
+            timestamp_epoch: required(&self.timestamp_epoch, "timestamp_epoch")?,
+            verifying_key: required(&self.verifying_key, "verifying_key")?,
+            encrypting_key: required(&self.encrypting_key, "encrypting_key")?,
+            certificate_der: required(&self.certificate_der, "certificate_der")?,
+            host: required(&self.host, "host")?,
+            port: required(&self.port, "port")?,
+            operator_signature: self.operator_signature,
+        })
+    }
 }
 
 /// Signed node metadata.
@@ -144,7 +282,7 @@ impl NodeMetadata {
     pub fn new(signer: &Signer, payload: &NodeMetadataPayload) -> Self {
         // TODO: how can we ensure that `verifying_key` in `payload` is the same as in `signer`?
         Self {
-            signature: signer.sign(&payload.to_bytes()),
+            signature: signer.sign(&payload.signed_bytes()),
             payload: payload.clone(),
         }
     }
@@ -159,10 +297,47 @@ impl NodeMetadata {
 
         // We could do this on deserialization, but it is a relatively expensive operation.
         self.signature
-            .verify(&self.payload.verifying_key, &self.payload.to_bytes())
+            .verify(&self.payload.verifying_key, &self.signed_bytes())
+    }
+
+    /// Returns the signature over [`signed_bytes`](`Self::signed_bytes`).
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the serialized payload the signature was created for,
+    /// allowing one to verify the signature externally.
+    pub fn signed_bytes(&self) -> Box<[u8]> {
+        self.payload.signed_bytes()
+    }
+}
+
+impl<'a> ProtocolObjectInner<'a> for NodeMetadataPayload {
+    fn brand() -> [u8; 4] {
+        *b"NdPl"
+    }
+
+    fn version() -> (u16, u16) {
+        // Same as for `NodeMetadata`: adding a field requires a major version change,
+        // since the payload is signed.
+        (1, 0)
+    }
+
+    fn unversioned_to_bytes(&self) -> Box<[u8]> {
+        messagepack_serialize(&self)
+    }
+
+    fn unversioned_from_bytes(minor_version: u16, bytes: &[u8]) -> Option<Result<Self, String>> {
+        if minor_version == 0 {
+            Some(messagepack_deserialize(bytes))
+        } else {
+            None
+        }
     }
 }
 
+impl<'a> ProtocolObject<'a> for NodeMetadataPayload {}
+
 impl<'a> ProtocolObjectInner<'a> for NodeMetadata {
     fn brand() -> [u8; 4] {
         *b"NdMd"
@@ -191,6 +366,59 @@ impl<'a> ProtocolObjectInner<'a> for NodeMetadata {
 
 impl<'a> ProtocolObject<'a> for NodeMetadata {}
 
+/// The default maximum number of nodes in [`MetadataRequest`] and [`MetadataResponsePayload`].
+/// Also used as the limit when deserializing them.
+pub const MAX_ANNOUNCE_NODES: usize = 10000;
+
+/// Indicates that the number of announced nodes exceeds the allowed maximum.
+#[derive(Debug, PartialEq)]
+pub struct TooManyNodesError {
+    /// The maximum allowed number of nodes.
+    pub max_nodes: usize,
+    /// The number of nodes given.
+    pub received: usize,
+}
+
+impl fmt::Display for TooManyNodesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "too many announced nodes: expected at most {}, got {}",
+            self.max_nodes, self.received
+        )
+    }
+}
+
+/// Indicates that an announced node does not fit into a response of the requested size.
+#[derive(Debug, PartialEq)]
+pub struct NodeTooLargeError {
+    /// The requested maximum size of a serialized response.
+    pub max_bytes: usize,
+    /// The size of a serialized response containing only this node.
+    pub received: usize,
+}
+
+impl fmt::Display for NodeTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "announced node does not fit into the response: expected at most {} bytes, got {}",
+            self.max_bytes, self.received
+        )
+    }
+}
+
+fn check_announce_nodes(nodes: &[NodeMetadata], max_nodes: usize) -> Result<(), TooManyNodesError> {
+    if nodes.len() > max_nodes {
+        Err(TooManyNodesError {
+            max_nodes,
+            received: nodes.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
 /// A request for metadata exchange.
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
 pub struct MetadataRequest {
@@ -202,11 +430,34 @@ pub struct MetadataRequest {
 
 impl MetadataRequest {
     /// Creates a new request.
+    ///
+    /// Panics if there are more than [`MAX_ANNOUNCE_NODES`] nodes in `announce_nodes`
+    /// (see [`try_new`](`Self::try_new`) for a fallible version).
     pub fn new(fleet_state_checksum: &FleetStateChecksum, announce_nodes: &[NodeMetadata]) -> Self {
-        Self {
+        Self::try_new(fleet_state_checksum, announce_nodes).unwrap()
+    }
+
+    /// Creates a new request.
+    ///
+    /// Fails if there are more than [`MAX_ANNOUNCE_NODES`] nodes in `announce_nodes`.
+    pub fn try_new(
+        fleet_state_checksum: &FleetStateChecksum,
+        announce_nodes: &[NodeMetadata],
+    ) -> Result<Self, TooManyNodesError> {
+        Self::new_with_max_nodes(fleet_state_checksum, announce_nodes, MAX_ANNOUNCE_NODES)
+    }
+
+    /// Creates a new request, checking the number of nodes against a custom limit.
+    pub fn new_with_max_nodes(
+        fleet_state_checksum: &FleetStateChecksum,
+        announce_nodes: &[NodeMetadata],
+        max_nodes: usize,
+    ) -> Result<Self, TooManyNodesError> {
+        check_announce_nodes(announce_nodes, max_nodes)?;
+        Ok(Self {
             fleet_state_checksum: *fleet_state_checksum,
             announce_nodes: announce_nodes.to_vec().into_boxed_slice(),
-        }
+        })
     }
 }
 
@@ -230,6 +481,11 @@ impl<'a> ProtocolObjectInner<'a> for MetadataRequest {
             None
         }
     }
+
+    fn validate_decoded(&self) -> Result<(), String> {
+        check_announce_nodes(&self.announce_nodes, MAX_ANNOUNCE_NODES)
+            .map_err(|err| format!("{}", err))
+    }
 }
 
 impl<'a> ProtocolObject<'a> for MetadataRequest {}
@@ -246,19 +502,140 @@ pub struct MetadataResponsePayload {
 
 impl MetadataResponsePayload {
     /// Creates the new metadata response payload.
+    ///
+    /// Panics if there are more than [`MAX_ANNOUNCE_NODES`] nodes in `announce_nodes`
+    /// (see [`try_new`](`Self::try_new`) for a fallible version).
     pub fn new(timestamp_epoch: u32, announce_nodes: &[NodeMetadata]) -> Self {
-        Self {
+        Self::try_new(timestamp_epoch, announce_nodes).unwrap()
+    }
+
+    /// Creates the new metadata response payload.
+    ///
+    /// Fails if there are more than [`MAX_ANNOUNCE_NODES`] nodes in `announce_nodes`.
+    pub fn try_new(
+        timestamp_epoch: u32,
+        announce_nodes: &[NodeMetadata],
+    ) -> Result<Self, TooManyNodesError> {
+        Self::new_with_max_nodes(timestamp_epoch, announce_nodes, MAX_ANNOUNCE_NODES)
+    }
+
+    /// Creates the new metadata response payload,
+    /// checking the number of nodes against a custom limit.
+    pub fn new_with_max_nodes(
+        timestamp_epoch: u32,
+        announce_nodes: &[NodeMetadata],
+        max_nodes: usize,
+    ) -> Result<Self, TooManyNodesError> {
+        check_announce_nodes(announce_nodes, max_nodes)?;
+        Ok(Self {
             timestamp_epoch,
             announce_nodes: announce_nodes.to_vec().into_boxed_slice(),
-        }
+        })
+    }
+
+    /// Returns the sorted list of distinct domains of the announced nodes.
+    pub fn domains(&self) -> Vec<String> {
+        self.announce_nodes
+            .iter()
+            .map(|node| node.payload.domain.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns `true` if this payload has a later timestamp than `other`.
+    /// Payloads with equal timestamps are not fresher than each other.
+    pub fn is_fresher_than(&self, other: &MetadataResponsePayload) -> bool {
+        self.timestamp_epoch > other.timestamp_epoch
+    }
+
+    /// Returns a serialization of the announced nodes that does not depend on their order,
+    /// to be hashed for comparing the node sets of different peers.
+    ///
+    /// It is not the wire format; the timestamp is not included.
+    pub fn canonical_bytes(&self) -> Box<[u8]> {
+        let mut nodes_bytes: Vec<Box<[u8]>> = self
+            .announce_nodes
+            .iter()
+            .map(|node| node.to_bytes())
+            .collect();
+        nodes_bytes.sort_unstable();
+        // The serialized nodes are self-delimiting, so they can be just concatenated.
+        nodes_bytes.concat().into_boxed_slice()
     }
 
     // Standard payload serialization for signing purposes.
-    fn to_bytes(&self) -> Box<[u8]> {
+    // Same as `unversioned_to_bytes()`, that is without the header,
+    // so that the signatures do not depend on the payload version.
+    fn signed_bytes(&self) -> Box<[u8]> {
         messagepack_serialize(self)
     }
 }
 
+impl<'a> ProtocolObjectInner<'a> for MetadataResponsePayload {
+    fn brand() -> [u8; 4] {
+        *b"MdPl"
+    }
+
+    fn version() -> (u16, u16) {
+        // Same as for `MetadataResponse`: adding a field requires a major version change,
+        // since the payload is signed.
+        (1, 0)
+    }
+
+    fn unversioned_to_bytes(&self) -> Box<[u8]> {
+        messagepack_serialize(&self)
+    }
+
+    fn unversioned_from_bytes(minor_version: u16, bytes: &[u8]) -> Option<Result<Self, String>> {
+        if minor_version == 0 {
+            Some(messagepack_deserialize(bytes))
+        } else {
+            None
+        }
+    }
+
+    fn validate_decoded(&self) -> Result<(), String> {
+        check_announce_nodes(&self.announce_nodes, MAX_ANNOUNCE_NODES)
+            .map_err(|err| format!("{}", err))
+    }
+}
+
+impl<'a> ProtocolObject<'a> for MetadataResponsePayload {}
+
+/// Assembles a [`MetadataResponsePayload`] one node at a time.
+///
+/// Nodes with the same staking provider address are deduplicated (the newest one is kept),
+/// and the resulting list is put in canonical order (see [`canonicalize_nodes`]).
+#[derive(Debug, Clone, Default)]
+pub struct MetadataResponsePayloadBuilder {
+    announce_nodes: Vec<NodeMetadata>,
+}
+
+impl MetadataResponsePayloadBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node to be announced.
+    pub fn add_node(&mut self, node: NodeMetadata) -> &mut Self {
+        self.announce_nodes.push(node);
+        self
+    }
+
+    /// Creates the payload with the given timestamp.
+    ///
+    /// Fails if there are more than [`MAX_ANNOUNCE_NODES`] distinct nodes.
+    pub fn build(
+        &self,
+        timestamp_epoch: u32,
+    ) -> Result<MetadataResponsePayload, TooManyNodesError> {
+        let announce_nodes = canonicalize_nodes(self.announce_nodes.clone());
+        MetadataResponsePayload::try_new(timestamp_epoch, &announce_nodes)
+    }
+}
+
 /// A response returned by an Ursula containing known node metadata.
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
 pub struct MetadataResponse {
@@ -270,7 +647,7 @@ impl MetadataResponse {
     /// Creates and signs a new metadata response.
     pub fn new(signer: &Signer, payload: &MetadataResponsePayload) -> Self {
         Self {
-            signature: signer.sign(&payload.to_bytes()),
+            signature: signer.sign(&payload.signed_bytes()),
             payload: payload.clone(),
         }
     }
@@ -282,13 +659,77 @@ impl MetadataResponse {
     ) -> Result<MetadataResponsePayload, VerificationError> {
         if self
             .signature
-            .verify(verifying_pk, &self.payload.to_bytes())
+            .verify(verifying_pk, &self.payload.signed_bytes())
         {
             Ok(self.payload)
         } else {
             Err(VerificationError)
         }
     }
+
+    /// Returns the signature over [`signed_bytes`](`Self::signed_bytes`).
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the serialized payload the signature was created for,
+    /// allowing one to verify the signature externally.
+    pub fn signed_bytes(&self) -> Box<[u8]> {
+        self.payload.signed_bytes()
+    }
+
+    /// Splits the announced nodes between several responses (with the same timestamp),
+    /// each serialized into at most `max_bytes` bytes and signed with `signer`.
+    ///
+    /// Fails if a response with a single node would be larger than `max_bytes`.
+    pub fn split_by_size(
+        &self,
+        signer: &Signer,
+        max_bytes: usize,
+    ) -> Result<Vec<MetadataResponse>, NodeTooLargeError> {
+        let timestamp_epoch = self.payload.timestamp_epoch;
+        let make_response = |nodes: &[NodeMetadata]| {
+            let payload = MetadataResponsePayload {
+                timestamp_epoch,
+                announce_nodes: nodes.to_vec().into_boxed_slice(),
+            };
+            MetadataResponse::new(signer, &payload)
+        };
+
+        // The signature has a fixed size, so the size of a response is the size
+        // of an empty one plus the sizes of the nodes and the growth of the array header.
+        let empty_len = make_response(&[]).to_bytes().len();
+        let response_len = |nodes_len: usize, nodes_count: usize| {
+            empty_len + nodes_len + messagepack_array_header_len(nodes_count)
+                - messagepack_array_header_len(0)
+        };
+
+        let mut chunks = Vec::<&[NodeMetadata]>::new();
+        let mut start = 0;
+        let mut nodes_len = 0;
+        for (i, node) in self.payload.announce_nodes.iter().enumerate() {
+            let node_len = messagepack_serialize(node).len();
+            let count = i - start + 1;
+            if count > MAX_ANNOUNCE_NODES || response_len(nodes_len + node_len, count) > max_bytes {
+                let single_len = response_len(node_len, 1);
+                if single_len > max_bytes {
+                    return Err(NodeTooLargeError {
+                        max_bytes,
+                        received: single_len,
+                    });
+                }
+                chunks.push(&self.payload.announce_nodes[start..i]);
+                start = i;
+                nodes_len = 0;
+            }
+            nodes_len += node_len;
+        }
+        if start < self.payload.announce_nodes.len() || chunks.is_empty() {
+            chunks.push(&self.payload.announce_nodes[start..]);
+        }
+
+        Ok(chunks.into_iter().map(make_response).collect())
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for MetadataResponse {
@@ -316,6 +757,11 @@ impl<'a> ProtocolObjectInner<'a> for MetadataResponse {
             None
         }
     }
+
+    fn validate_decoded(&self) -> Result<(), String> {
+        check_announce_nodes(&self.payload.announce_nodes, MAX_ANNOUNCE_NODES)
+            .map_err(|err| format!("{}", err))
+    }
 }
 
 impl<'a> ProtocolObject<'a> for MetadataResponse {}
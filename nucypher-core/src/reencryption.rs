@@ -1,14 +1,17 @@
 use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
 
 use serde::{Deserialize, Serialize};
 use umbral_pre::{
-    Capsule, CapsuleFrag, PublicKey, SerializableToArray, Signature, Signer, VerifiedCapsuleFrag,
+    Capsule, CapsuleFrag, DeserializableFromArray,
+    DeserializationError as CfragDeserializationError, PublicKey, SecretKey, SerializableToArray,
+    Signature, Signer, VerifiedCapsuleFrag,
 };
 
-use crate::hrac::HRAC;
-use crate::key_frag::EncryptedKeyFrag;
+use crate::hrac::{HasHrac, HRAC};
+use crate::key_frag::{DecryptionError, EncryptedKeyFrag};
 use crate::versioning::{
     messagepack_deserialize, messagepack_serialize, ProtocolObject, ProtocolObjectInner,
 };
@@ -46,6 +49,22 @@ impl ReencryptionRequest {
             bob_verifying_key: *bob_verifying_key,
         }
     }
+
+    /// Checks that the encrypted key frag in the request is addressed to the Ursula
+    /// with the secret key `ursula_sk`, and was created by the publisher for this policy.
+    ///
+    /// This is a cheap sanity check to be performed before any other processing.
+    pub fn precheck(&self, ursula_sk: &SecretKey) -> Result<(), DecryptionError> {
+        self.encrypted_kfrag
+            .decrypt(ursula_sk, &self.hrac, &self.publisher_verifying_key)
+            .map(|_verified_kfrag| ())
+    }
+}
+
+impl HasHrac for ReencryptionRequest {
+    fn referenced_hrac(&self) -> Option<HRAC> {
+        Some(self.hrac)
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for ReencryptionRequest {
@@ -72,6 +91,35 @@ impl<'a> ProtocolObjectInner<'a> for ReencryptionRequest {
 
 impl<'a> ProtocolObject<'a> for ReencryptionRequest {}
 
+/// Errors that can happen when creating a [`ReencryptionResponse`] from serialized capsule frags.
+#[derive(Debug, PartialEq)]
+pub enum CfragBytesError {
+    /// The number of capsule frags differs from the number of capsules.
+    CountMismatch {
+        /// The number of capsules.
+        capsules: usize,
+        /// The number of serialized capsule frags.
+        cfrags: usize,
+    },
+    /// Failed to deserialize a capsule frag.
+    DeserializationFailed(CfragDeserializationError),
+}
+
+impl fmt::Display for CfragBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CountMismatch { capsules, cfrags } => write!(
+                f,
+                "the number of capsule frags ({}) differs from the number of capsules ({})",
+                cfrags, capsules
+            ),
+            Self::DeserializationFailed(err) => {
+                write!(f, "failed to deserialize a capsule frag: {}", err)
+            }
+        }
+    }
+}
+
 /// A response from Ursula with reencrypted capsule frags.
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
 pub struct ReencryptionResponse {
@@ -114,6 +162,37 @@ impl ReencryptionResponse {
         }
     }
 
+    /// Creates and signs a new reencryption response from serialized capsule frags
+    /// (e.g. the ones stored by an Ursula).
+    ///
+    /// The capsule frags are only deserialized here, not verified;
+    /// it is the caller's responsibility to make sure they were created for `capsules`.
+    pub fn from_cfrag_bytes(
+        signer: &Signer,
+        capsules: &[Capsule],
+        cfrag_bytes: &[impl AsRef<[u8]>],
+    ) -> Result<Self, CfragBytesError> {
+        if capsules.len() != cfrag_bytes.len() {
+            return Err(CfragBytesError::CountMismatch {
+                capsules: capsules.len(),
+                cfrags: cfrag_bytes.len(),
+            });
+        }
+
+        let cfrags = cfrag_bytes
+            .iter()
+            .map(CapsuleFrag::from_bytes)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(CfragBytesError::DeserializationFailed)?;
+
+        let signature = signer.sign(&signed_message(capsules, &cfrags));
+
+        Ok(ReencryptionResponse {
+            cfrags: cfrags.into_boxed_slice(),
+            signature,
+        })
+    }
+
     /// Verifies the reencryption response and returns the contained kfrags on success.
     pub fn verify(
         &self,
@@ -157,6 +236,94 @@ impl ReencryptionResponse {
             .map(|vcfrags| vcfrags.into_boxed_slice())
             .map_err(|_err| VerificationError)
     }
+
+    /// Verifies the capsule frags one by one, returning the result for each capsule
+    /// (in the same order as `capsules`), so that the valid ones can be used
+    /// even if some of them are invalid.
+    ///
+    /// Fails if the number of capsule frags does not match the number of capsules,
+    /// or if the response was not signed by the Ursula.
+    pub fn verify_each(
+        &self,
+        capsules: &[Capsule],
+        alice_verifying_key: &PublicKey,
+        ursula_verifying_key: &PublicKey,
+        policy_encrypting_key: &PublicKey,
+        bob_encrypting_key: &PublicKey,
+    ) -> Result<Vec<Result<VerifiedCapsuleFrag, VerificationError>>, VerificationError> {
+        if capsules.len() != self.cfrags.len() {
+            return Err(VerificationError);
+        }
+
+        if !self.signature.verify(
+            ursula_verifying_key,
+            &signed_message(capsules, &self.cfrags),
+        ) {
+            return Err(VerificationError);
+        }
+
+        Ok(self
+            .cfrags
+            .iter()
+            .cloned()
+            .zip(capsules.iter())
+            .map(|(cfrag, capsule)| {
+                cfrag
+                    .verify(
+                        capsule,
+                        alice_verifying_key,
+                        policy_encrypting_key,
+                        bob_encrypting_key,
+                    )
+                    .map_err(|_err| VerificationError)
+            })
+            .collect())
+    }
+
+    /// Verifies the capsule frags one by one and returns the capsules that have a valid one,
+    /// along with a new response containing only the valid capsule frags.
+    ///
+    /// The original signature does not cover the filtered list,
+    /// so the new response is signed with `signer` (e.g. the client caching it),
+    /// and must be verified with its verifying key instead of the Ursula's one.
+    ///
+    /// Fails if the number of capsule frags does not match the number of capsules,
+    /// or if the original response was not signed by the Ursula.
+    pub fn filter_valid(
+        &self,
+        signer: &Signer,
+        capsules: &[Capsule],
+        alice_verifying_key: &PublicKey,
+        ursula_verifying_key: &PublicKey,
+        policy_encrypting_key: &PublicKey,
+        bob_encrypting_key: &PublicKey,
+    ) -> Result<(Box<[Capsule]>, Self), VerificationError> {
+        let results = self.verify_each(
+            capsules,
+            alice_verifying_key,
+            ursula_verifying_key,
+            policy_encrypting_key,
+            bob_encrypting_key,
+        )?;
+
+        let (valid_capsules, vcfrags): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .zip(capsules.iter())
+            .filter_map(|(result, capsule)| result.ok().map(|vcfrag| (*capsule, vcfrag)))
+            .unzip();
+
+        let response = Self::new(signer, &valid_capsules, vcfrags);
+        Ok((valid_capsules.into_boxed_slice(), response))
+    }
+
+    /// Returns the serialized (unverified) capsule frags, e.g. for storage.
+    /// They will have to be verified again after deserialization.
+    pub fn into_unverified_cfrags(self) -> Vec<Box<[u8]>> {
+        self.cfrags
+            .iter()
+            .map(|cfrag| cfrag.to_array().to_vec().into_boxed_slice())
+            .collect()
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for ReencryptionResponse {
@@ -182,3 +349,130 @@ impl<'a> ProtocolObjectInner<'a> for ReencryptionResponse {
 }
 
 impl<'a> ProtocolObject<'a> for ReencryptionResponse {}
+
+/// Collects capsule frags from reencryption responses as they arrive,
+/// verifying each response on addition.
+#[derive(Debug, Clone)]
+pub struct CfragCollector {
+    capsules: Box<[Capsule]>,
+    alice_verifying_key: PublicKey,
+    policy_encrypting_key: PublicKey,
+    bob_encrypting_key: PublicKey,
+    threshold: u8,
+    ursula_verifying_keys: Vec<PublicKey>,
+    vcfrags: Vec<Vec<VerifiedCapsuleFrag>>,
+}
+
+impl CfragCollector {
+    /// Creates a new collector for responses to a reencryption request for `capsules`.
+    pub fn new(
+        capsules: &[Capsule],
+        alice_verifying_key: &PublicKey,
+        policy_encrypting_key: &PublicKey,
+        bob_encrypting_key: &PublicKey,
+        threshold: u8,
+    ) -> Self {
+        Self {
+            capsules: capsules.into(),
+            alice_verifying_key: *alice_verifying_key,
+            policy_encrypting_key: *policy_encrypting_key,
+            bob_encrypting_key: *bob_encrypting_key,
+            threshold,
+            ursula_verifying_keys: Vec::new(),
+            vcfrags: capsules.iter().map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Verifies a response from an Ursula and adds the contained capsule frags.
+    ///
+    /// A repeated response from the same Ursula is ignored.
+    pub fn add(
+        &mut self,
+        response: &ReencryptionResponse,
+        ursula_verifying_key: &PublicKey,
+    ) -> Result<(), VerificationError> {
+        if self.ursula_verifying_keys.contains(ursula_verifying_key) {
+            return Ok(());
+        }
+
+        let vcfrags = response.verify(
+            &self.capsules,
+            &self.alice_verifying_key,
+            ursula_verifying_key,
+            &self.policy_encrypting_key,
+            &self.bob_encrypting_key,
+        )?;
+
+        for (capsule_vcfrags, vcfrag) in self.vcfrags.iter_mut().zip(vcfrags.iter()) {
+            capsule_vcfrags.push(vcfrag.clone());
+        }
+        self.ursula_verifying_keys.push(*ursula_verifying_key);
+        Ok(())
+    }
+
+    /// Returns the number of responses added so far.
+    pub fn responses_count(&self) -> usize {
+        self.ursula_verifying_keys.len()
+    }
+
+    /// Returns `true` if enough responses were collected to decrypt the capsules.
+    pub fn is_complete(&self) -> bool {
+        self.responses_count() >= self.threshold as usize
+    }
+
+    /// Returns the verified capsule frags collected for the capsule with the given index
+    /// (in the order the capsules were given to [`new`](`Self::new`)).
+    pub fn cfrags(&self, capsule_index: usize) -> Option<&[VerifiedCapsuleFrag]> {
+        self.vcfrags
+            .get(capsule_index)
+            .map(|vcfrags| vcfrags.as_slice())
+    }
+}
+
+/// Keeps track of reencryption responses by the Ursulas that sent them,
+/// so that repeated responses from the same Ursula are not counted twice.
+///
+/// Unlike [`CfragCollector`], the responses are not verified on addition.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseAggregator {
+    responses: Vec<(PublicKey, ReencryptionResponse)>,
+}
+
+impl ResponseAggregator {
+    /// Creates an empty aggregator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a response from the Ursula with the given verifying key.
+    ///
+    /// Returns `false` (and does not add the response)
+    /// if a response from this Ursula was already added.
+    pub fn add(
+        &mut self,
+        ursula_verifying_key: &PublicKey,
+        response: &ReencryptionResponse,
+    ) -> bool {
+        if self
+            .responses
+            .iter()
+            .any(|(verifying_key, _response)| verifying_key == ursula_verifying_key)
+        {
+            return false;
+        }
+        self.responses
+            .push((*ursula_verifying_key, response.clone()));
+        true
+    }
+
+    /// Returns the number of Ursulas the responses were received from.
+    pub fn unique_count(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Returns the added responses along with the verifying keys of their Ursulas,
+    /// in the order of addition.
+    pub fn responses(&self) -> &[(PublicKey, ReencryptionResponse)] {
+        &self.responses
+    }
+}
@@ -1,6 +1,7 @@
 use alloc::boxed::Box;
 use alloc::collections::BTreeSet;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use serde::{Deserialize, Serialize};
 use umbral_pre::Capsule;
@@ -64,3 +65,43 @@ impl<'a> ProtocolObjectInner<'a> for RetrievalKit {
 }
 
 impl<'a> ProtocolObject<'a> for RetrievalKit {}
+
+/// Retrieval information for several messages encrypted under the same policy,
+/// sharing the set of Ursulas already queried.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MultiRetrievalKit {
+    /// The capsules of the messages.
+    pub capsules: Box<[Capsule]>,
+    /// The addresses that have already been queried for reencryption.
+    pub queried_addresses: BTreeSet<Address>,
+}
+
+impl MultiRetrievalKit {
+    /// Creates a new retrieval kit for the given message kits.
+    pub fn from_message_kits(message_kits: &[MessageKit]) -> Self {
+        Self {
+            capsules: message_kits
+                .iter()
+                .map(|message_kit| message_kit.capsule)
+                .collect(),
+            queried_addresses: BTreeSet::<Address>::new(),
+        }
+    }
+
+    /// Creates a new retrieval kit recording the addresses already queried for reencryption.
+    pub fn new(capsules: &[Capsule], queried_addresses: impl IntoIterator<Item = Address>) -> Self {
+        Self {
+            capsules: capsules.into(),
+            queried_addresses: queried_addresses.into_iter().collect(),
+        }
+    }
+
+    /// Splits the kit into separate retrieval kits for each capsule
+    /// (e.g. for serialization).
+    pub fn retrieval_kits(&self) -> Vec<RetrievalKit> {
+        self.capsules
+            .iter()
+            .map(|capsule| RetrievalKit::new(capsule, self.queried_addresses.iter().copied()))
+            .collect()
+    }
+}
@@ -1,10 +1,14 @@
 use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 
 use serde::{Deserialize, Serialize};
-use umbral_pre::{PublicKey, Signature, Signer};
+use sha3::{Digest, Keccak256};
+use umbral_pre::{PublicKey, SerializableToArray, Signature, Signer};
 
 use crate::address::Address;
+use crate::hrac::{HasHrac, HRAC};
 use crate::key_frag::EncryptedKeyFrag;
 use crate::versioning::{
     messagepack_deserialize, messagepack_serialize, ProtocolObject, ProtocolObjectInner,
@@ -18,6 +22,27 @@ pub struct RevocationOrder {
     staking_provider_address: Address,
     encrypted_kfrag: EncryptedKeyFrag,
     signature: Signature,
+    /// Orders serialized with version 1.0 are deserialized with this field set to `None`.
+    #[serde(default)]
+    issued_at: Option<u32>,
+}
+
+fn signed_message(
+    staking_provider_address: &Address,
+    encrypted_kfrag: &EncryptedKeyFrag,
+    issued_at: Option<u32>,
+) -> Vec<u8> {
+    let mut message = [
+        staking_provider_address.as_ref(),
+        &encrypted_kfrag.to_bytes(),
+    ]
+    .concat();
+    // Appended only if present, so that the signatures of the orders without it
+    // are the same as in version 1.0.
+    if let Some(issued_at) = issued_at {
+        message.extend(issued_at.to_be_bytes());
+    }
+    message
 }
 
 impl RevocationOrder {
@@ -26,37 +51,122 @@ impl RevocationOrder {
         signer: &Signer,
         staking_provider_address: &Address,
         encrypted_kfrag: &EncryptedKeyFrag,
+    ) -> Self {
+        Self::new_with_issued_at(signer, staking_provider_address, encrypted_kfrag, None)
+    }
+
+    /// Create and sign a new revocation order.
+    ///
+    /// `issued_at` is the epoch of the order's creation, if it needs to be checked
+    /// against the policy (see [`is_after`](`Self::is_after`)). It is covered by the signature.
+    pub fn new_with_issued_at(
+        signer: &Signer,
+        staking_provider_address: &Address,
+        encrypted_kfrag: &EncryptedKeyFrag,
+        issued_at: Option<u32>,
     ) -> Self {
         Self {
             staking_provider_address: *staking_provider_address,
             encrypted_kfrag: encrypted_kfrag.clone(),
-            signature: signer.sign(
-                &[
-                    staking_provider_address.as_ref(),
-                    &encrypted_kfrag.to_bytes(),
-                ]
-                .concat(),
-            ),
+            signature: signer.sign(&signed_message(
+                staking_provider_address,
+                encrypted_kfrag,
+                issued_at,
+            )),
+            issued_at,
         }
     }
 
+    /// Returns the address of the Ursula that is being revoked.
+    ///
+    /// Note that it is not authenticated until the order is checked with
+    /// [`verify`](`Self::verify`).
+    pub fn staking_provider_address(&self) -> &Address {
+        &self.staking_provider_address
+    }
+
+    /// Returns the encrypted keyfrag of the Ursula that is being revoked.
+    ///
+    /// Note that it is not authenticated until the order is checked with
+    /// [`verify`](`Self::verify`).
+    pub fn encrypted_kfrag(&self) -> &EncryptedKeyFrag {
+        &self.encrypted_kfrag
+    }
+
+    /// Returns the epoch of the order's creation, if it was set.
+    pub fn issued_at(&self) -> Option<u32> {
+        self.issued_at
+    }
+
+    /// Returns `true` if the order was issued after the given epoch
+    /// (e.g. the creation of the policy it revokes).
+    /// Returns `false` if the order does not have an issue epoch.
+    ///
+    /// Note that the epoch is only authenticated by [`verify`](`Self::verify`).
+    pub fn is_after(&self, epoch: u32) -> bool {
+        self.issued_at.is_some_and(|issued_at| issued_at > epoch)
+    }
+
     /// Verifies the revocation order against Alice's key.
     /// On success, returns the staking provider address and the encrypted keyfrag.
     pub fn verify(
         self,
         alice_verifying_key: &PublicKey,
     ) -> Result<(Address, EncryptedKeyFrag), VerificationError> {
-        let message = [
-            self.staking_provider_address.as_ref(),
-            &self.encrypted_kfrag.to_bytes(),
-        ]
-        .concat();
+        let message = signed_message(
+            &self.staking_provider_address,
+            &self.encrypted_kfrag,
+            self.issued_at,
+        );
         if self.signature.verify(alice_verifying_key, &message) {
             Ok((self.staking_provider_address, self.encrypted_kfrag))
         } else {
             Err(VerificationError)
         }
     }
+
+    /// Returns the revocation order in the form expected by the on-chain revocation contracts:
+    /// the staking provider address, the Keccak-256 hash of the serialized encrypted keyfrag,
+    /// and the signature bytes.
+    ///
+    /// Fails if the order has an issue epoch: its signature covers the epoch as well,
+    /// which the contracts do not receive, so they would not be able to verify it.
+    pub fn to_chain_tuple(&self) -> Result<(Address, [u8; 32], Vec<u8>), ChainTupleError> {
+        if let Some(issued_at) = self.issued_at {
+            return Err(ChainTupleError { issued_at });
+        }
+        let ekfrag_hash = Keccak256::digest(&self.encrypted_kfrag.to_bytes());
+        Ok((
+            self.staking_provider_address,
+            ekfrag_hash.into(),
+            self.signature.to_array().to_vec(),
+        ))
+    }
+}
+
+/// Indicates that a revocation order cannot be submitted to the on-chain revocation contracts.
+#[derive(Debug, PartialEq)]
+pub struct ChainTupleError {
+    /// The issue epoch of the order, which is covered by its signature,
+    /// but is not passed to the contracts.
+    pub issued_at: u32,
+}
+
+impl fmt::Display for ChainTupleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the order has an issue epoch ({}), so its signature cannot be verified on-chain",
+            self.issued_at
+        )
+    }
+}
+
+impl HasHrac for RevocationOrder {
+    fn referenced_hrac(&self) -> Option<HRAC> {
+        // The HRAC is only available inside the encrypted kfrag.
+        None
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for RevocationOrder {
@@ -65,7 +175,8 @@ impl<'a> ProtocolObjectInner<'a> for RevocationOrder {
     }
 
     fn version() -> (u16, u16) {
-        (1, 0)
+        // Version 1.1 added `issued_at`.
+        (1, 1)
     }
 
     fn unversioned_to_bytes(&self) -> Box<[u8]> {
@@ -73,7 +184,8 @@ impl<'a> ProtocolObjectInner<'a> for RevocationOrder {
     }
 
     fn unversioned_from_bytes(minor_version: u16, bytes: &[u8]) -> Option<Result<Self, String>> {
-        if minor_version == 0 {
+        // The missing `issued_at` of version 1.0 is filled in by `serde(default)`.
+        if minor_version <= 1 {
             Some(messagepack_deserialize(bytes))
         } else {
             None
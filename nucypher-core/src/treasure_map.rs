@@ -3,21 +3,102 @@ use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Sha3_256};
 use umbral_pre::{
     decrypt_original, encrypt, Capsule, EncryptionError, PublicKey, SecretKey, SerializableToArray,
     Signature, Signer, VerifiedKeyFrag,
 };
 
 use crate::address::Address;
-use crate::hrac::HRAC;
+use crate::hrac::{HasHrac, HRAC};
 use crate::key_frag::{DecryptionError, EncryptedKeyFrag};
 use crate::versioning::{
-    messagepack_deserialize, messagepack_serialize, ProtocolObject, ProtocolObjectInner,
+    messagepack_bytes_len, messagepack_deserialize, messagepack_map_header_len,
+    messagepack_serialize, messagepack_uint_len, DeserializationError, ProtocolObject,
+    ProtocolObjectInner, HEADER_SIZE,
 };
 use crate::RevocationOrder;
 
+#[cfg(any(test, not(feature = "parallel")))]
+fn encrypt_kfrags_serial(
+    signer: &Signer,
+    hrac: &HRAC,
+    assigned_kfrags: Vec<(Address, (PublicKey, VerifiedKeyFrag))>,
+) -> Vec<(Address, EncryptedKeyFrag)> {
+    assigned_kfrags
+        .into_iter()
+        .map(
+            |(ursula_address, (ursula_encrypting_key, verified_kfrag))| {
+                let encrypted_kfrag =
+                    EncryptedKeyFrag::new(signer, &ursula_encrypting_key, hrac, verified_kfrag);
+                (ursula_address, encrypted_kfrag)
+            },
+        )
+        .collect()
+}
+
+// Encrypting kfrags is the most expensive part of creating a treasure map,
+// and each one is independent of others, so for large policies it pays off
+// to do it concurrently.
+#[cfg(feature = "parallel")]
+fn encrypt_kfrags_parallel(
+    signer: &Signer,
+    hrac: &HRAC,
+    assigned_kfrags: Vec<(Address, (PublicKey, VerifiedKeyFrag))>,
+) -> Vec<(Address, EncryptedKeyFrag)> {
+    use rayon::prelude::*;
+
+    assigned_kfrags
+        .into_par_iter()
+        .map(
+            |(ursula_address, (ursula_encrypting_key, verified_kfrag))| {
+                let encrypted_kfrag =
+                    EncryptedKeyFrag::new(signer, &ursula_encrypting_key, hrac, verified_kfrag);
+                (ursula_address, encrypted_kfrag)
+            },
+        )
+        .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+use encrypt_kfrags_serial as encrypt_kfrags;
+
+#[cfg(feature = "parallel")]
+use encrypt_kfrags_parallel as encrypt_kfrags;
+
+/// Indicates that the threshold of a treasure map is inconsistent with its destinations.
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    /// The threshold is 0.
+    ZeroThreshold,
+    /// The threshold is larger than the number of destinations.
+    ThresholdTooLarge {
+        /// The threshold of the treasure map.
+        threshold: u8,
+        /// The number of destinations in the treasure map.
+        destinations: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroThreshold => write!(f, "threshold must be non-zero"),
+            Self::ThresholdTooLarge {
+                threshold,
+                destinations,
+            } => write!(
+                f,
+                "threshold ({}) is larger than the number of destinations ({})",
+                threshold, destinations
+            ),
+        }
+    }
+}
+
 /// A structure containing `KeyFrag` objects encrypted for Ursulas chosen for this policy.
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct TreasureMap {
@@ -31,6 +112,10 @@ pub struct TreasureMap {
     pub policy_encrypting_key: PublicKey,
     /// Publisher's verifying key.
     pub publisher_verifying_key: PublicKey,
+    /// The epoch at which the policy expires (`None` if it does not expire).
+    /// Maps serialized with version 1.0 are deserialized with this field set to `None`.
+    #[serde(default)]
+    pub expiration_epoch: Option<u32>,
 }
 
 impl TreasureMap {
@@ -45,16 +130,35 @@ impl TreasureMap {
         policy_encrypting_key: &PublicKey,
         assigned_kfrags: impl IntoIterator<Item = (Address, (PublicKey, VerifiedKeyFrag))>,
         threshold: u8,
+    ) -> Self {
+        Self::new_with_expiration(
+            signer,
+            hrac,
+            policy_encrypting_key,
+            assigned_kfrags,
+            threshold,
+            None,
+        )
+    }
+
+    /// Same as [`TreasureMap::new`], but also records the epoch at which the policy expires.
+    pub fn new_with_expiration(
+        signer: &Signer,
+        hrac: &HRAC,
+        policy_encrypting_key: &PublicKey,
+        assigned_kfrags: impl IntoIterator<Item = (Address, (PublicKey, VerifiedKeyFrag))>,
+        threshold: u8,
+        expiration_epoch: Option<u32>,
     ) -> Self {
         // Panic here since violation of theis condition indicates a bug on the caller's side.
         assert!(threshold != 0, "threshold must be non-zero");
 
         // Encrypt each kfrag for an Ursula.
+        let assigned_kfrags: Vec<_> = assigned_kfrags.into_iter().collect();
+        let encrypted_kfrags = encrypt_kfrags(signer, hrac, assigned_kfrags);
+
         let mut destinations = BTreeMap::new();
-        for (ursula_address, (ursula_encrypting_key, verified_kfrag)) in assigned_kfrags.into_iter()
-        {
-            let encrypted_kfrag =
-                EncryptedKeyFrag::new(signer, &ursula_encrypting_key, hrac, verified_kfrag);
+        for (ursula_address, encrypted_kfrag) in encrypted_kfrags.into_iter() {
             if destinations
                 .insert(ursula_address, encrypted_kfrag)
                 .is_some()
@@ -80,7 +184,31 @@ impl TreasureMap {
             destinations,
             policy_encrypting_key: *policy_encrypting_key,
             publisher_verifying_key: signer.verifying_key(),
+            expiration_epoch,
+        }
+    }
+
+    /// Checks that the threshold is non-zero and does not exceed the number of destinations.
+    ///
+    /// Maps created with [`TreasureMap::new`] always satisfy it;
+    /// deserialized maps are checked in `from_bytes()`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.threshold == 0 {
+            return Err(ValidationError::ZeroThreshold);
+        }
+        if self.threshold as usize > self.destinations.len() {
+            return Err(ValidationError::ThresholdTooLarge {
+                threshold: self.threshold,
+                destinations: self.destinations.len(),
+            });
         }
+        Ok(())
+    }
+
+    /// Re-serializes a treasure map of any supported version with the current version,
+    /// e.g. to update the stored maps in bulk.
+    pub fn migrate(old_bytes: &[u8]) -> Result<Box<[u8]>, DeserializationError> {
+        Self::from_bytes(old_bytes).map(|treasure_map| treasure_map.to_bytes())
     }
 
     /// Encrypts the treasure map for Bob.
@@ -95,6 +223,151 @@ impl TreasureMap {
             .map(|(address, ekfrag)| RevocationOrder::new(signer, address, ekfrag))
             .collect()
     }
+
+    /// Returns the number of Ursulas the key frags are assigned to.
+    pub fn destination_count(&self) -> usize {
+        self.destinations.len()
+    }
+
+    /// Returns the number of Ursulas to contact so that, on average, `threshold` of them respond,
+    /// if each one fails independently with the probability `expected_failure_rate`.
+    ///
+    /// The count is `ceil(threshold / (1 - expected_failure_rate))`,
+    /// clamped to the range from `threshold` to the number of destinations.
+    /// A failure rate of 1 or more (or NaN) results in contacting all the destinations.
+    pub fn recommended_contact_count(&self, expected_failure_rate: f32) -> usize {
+        let threshold = self.threshold as usize;
+        let max_count = self.destinations.len();
+
+        if expected_failure_rate.is_nan() || expected_failure_rate >= 1.0 {
+            return max_count;
+        }
+        if expected_failure_rate <= 0.0 {
+            return threshold;
+        }
+
+        // `f32::ceil()` is not available in `no_std`, so rounding up is done by hand.
+        let exact_count = threshold as f32 / (1.0 - expected_failure_rate);
+        let mut count = exact_count as usize;
+        if (count as f32) < exact_count {
+            count += 1;
+        }
+
+        count.clamp(threshold, max_count)
+    }
+
+    /// Returns the encrypted key frag assigned to the Ursula with the given address, if any.
+    pub fn encrypted_kfrag_for(&self, address: &Address) -> Option<&EncryptedKeyFrag> {
+        self.destinations.get(address)
+    }
+
+    /// Returns the encrypted key frags assigned to the Ursulas from `reachable`,
+    /// ordered by address.
+    pub fn available_destinations(
+        &self,
+        reachable: &[Address],
+    ) -> Vec<(Address, EncryptedKeyFrag)> {
+        self.destinations
+            .iter()
+            .filter(|(address, _ekfrag)| reachable.contains(address))
+            .map(|(address, ekfrag)| (*address, ekfrag.clone()))
+            .collect()
+    }
+
+    /// Returns the encrypted key frags ordered by the address as compared on-chain,
+    /// that is as 160-bit unsigned integers.
+    pub fn destinations_chain_ordered(&self) -> Vec<(Address, &EncryptedKeyFrag)> {
+        // Addresses are fixed-size big-endian numbers, so their byte-wise ordering
+        // (the one used by the `destinations` `BTreeMap`) is the same as the numeric ordering.
+        self.destinations
+            .iter()
+            .map(|(address, ekfrag)| (*address, ekfrag))
+            .collect()
+    }
+
+    /// Returns `true` if the policy has an expiration epoch and `now_epoch` has reached it
+    /// (that is, the map is already expired at the expiration epoch itself).
+    pub fn is_expired(&self, now_epoch: u32) -> bool {
+        match self.expiration_epoch {
+            Some(expiration_epoch) => now_epoch >= expiration_epoch,
+            None => false,
+        }
+    }
+
+    /// Returns the number of verified capsule frags still needed to reach the threshold,
+    /// given that `verified_so_far` were already received.
+    pub fn responses_still_needed(&self, verified_so_far: usize) -> usize {
+        (self.threshold as usize).saturating_sub(verified_so_far)
+    }
+
+    /// Returns the length of the serialized object (the same as `to_bytes().len()`),
+    /// without actually serializing it.
+    pub fn serialized_len(&self) -> usize {
+        let destinations_len: usize = self
+            .destinations
+            .values()
+            .map(|ekfrag| {
+                // Encrypted kfrags are serialized inside the map without their own headers.
+                messagepack_bytes_len(Address::SIZE) + ekfrag.serialized_len() - HEADER_SIZE
+            })
+            .sum();
+
+        let expiration_epoch_len = match self.expiration_epoch {
+            Some(epoch) => messagepack_uint_len(epoch.into()),
+            // `nil`
+            None => 1,
+        };
+
+        // The struct is serialized as a 6-element array, which takes one byte for the prefix.
+        HEADER_SIZE
+            + 1
+            + messagepack_uint_len(self.threshold.into())
+            + messagepack_bytes_len(HRAC::SIZE)
+            + messagepack_map_header_len(self.destinations.len())
+            + destinations_len
+            + messagepack_bytes_len(self.policy_encrypting_key.to_array().len())
+            + messagepack_bytes_len(self.publisher_verifying_key.to_array().len())
+            + expiration_epoch_len
+    }
+
+    /// Returns `true` if both maps were created for the same policy:
+    /// they have the same HRAC, threshold, keys, expiration epoch, and destination addresses.
+    ///
+    /// Unlike `==`, the encrypted key frags themselves are not compared,
+    /// since their encryption is randomized.
+    pub fn same_policy_as(&self, other: &TreasureMap) -> bool {
+        self.hrac == other.hrac
+            && self.threshold == other.threshold
+            && self.policy_encrypting_key == other.policy_encrypting_key
+            && self.publisher_verifying_key == other.publisher_verifying_key
+            && self.expiration_epoch == other.expiration_epoch
+            && self.destinations.keys().eq(other.destinations.keys())
+    }
+
+    /// Returns a fingerprint identifying the policy this map was created for.
+    ///
+    /// It is a hash of the HRAC, the threshold, the publisher's verifying key,
+    /// and the destination addresses (in order).
+    /// The encrypted key frags are not included, since their encryption is randomized,
+    /// so all maps for which [`same_policy_as`](`Self::same_policy_as`) holds
+    /// have the same fingerprint.
+    pub fn policy_fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha3_256::new()
+            .chain(self.hrac.as_ref())
+            .chain([self.threshold])
+            .chain(self.publisher_verifying_key.to_array());
+        // `destinations` is a `BTreeMap`, so the iteration order is deterministic.
+        for address in self.destinations.keys() {
+            hasher.update(address.as_ref());
+        }
+        hasher.finalize().into()
+    }
+}
+
+impl HasHrac for TreasureMap {
+    fn referenced_hrac(&self) -> Option<HRAC> {
+        Some(self.hrac)
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for TreasureMap {
@@ -103,7 +376,8 @@ impl<'a> ProtocolObjectInner<'a> for TreasureMap {
     }
 
     fn version() -> (u16, u16) {
-        (1, 0)
+        // Version 1.1 added `expiration_epoch`.
+        (1, 1)
     }
 
     fn unversioned_to_bytes(&self) -> Box<[u8]> {
@@ -111,12 +385,17 @@ impl<'a> ProtocolObjectInner<'a> for TreasureMap {
     }
 
     fn unversioned_from_bytes(minor_version: u16, bytes: &[u8]) -> Option<Result<Self, String>> {
-        if minor_version == 0 {
+        // The missing `expiration_epoch` of version 1.0 is filled in by `serde(default)`.
+        if minor_version <= 1 {
             Some(messagepack_deserialize(bytes))
         } else {
             None
         }
     }
+
+    fn validate_decoded(&self) -> Result<(), String> {
+        self.validate().map_err(|err| format!("{}", err))
+    }
 }
 
 impl<'a> ProtocolObject<'a> for TreasureMap {}
@@ -225,6 +504,18 @@ impl EncryptedTreasureMap {
             .verify(&sk.public_key(), publisher_verifying_key)
             .ok_or(DecryptionError::VerificationFailed)
     }
+
+    /// Decrypts and verifies the treasure map,
+    /// returning only the encrypted key frags assigned to the Ursulas from `reachable`.
+    pub fn decrypt_available(
+        &self,
+        sk: &SecretKey,
+        publisher_verifying_key: &PublicKey,
+        reachable: &[Address],
+    ) -> Result<Vec<(Address, EncryptedKeyFrag)>, DecryptionError> {
+        let treasure_map = self.decrypt(sk, publisher_verifying_key)?;
+        Ok(treasure_map.available_destinations(reachable))
+    }
 }
 
 impl<'a> ProtocolObjectInner<'a> for EncryptedTreasureMap {
@@ -250,3 +541,212 @@ impl<'a> ProtocolObjectInner<'a> for EncryptedTreasureMap {
 }
 
 impl<'a> ProtocolObject<'a> for EncryptedTreasureMap {}
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveEntry {
+    hrac: HRAC,
+    // Each map is stored with its own header, so that the maps of different versions
+    // can be kept in the same archive.
+    #[serde(with = "serde_bytes")]
+    treasure_map: Box<[u8]>,
+}
+
+/// A collection of treasure maps serialized together, e.g. for bulk storage.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TreasureMapArchive {
+    treasure_maps: Vec<TreasureMap>,
+}
+
+impl TreasureMapArchive {
+    /// Creates a new archive from the given treasure maps.
+    pub fn new(treasure_maps: &[TreasureMap]) -> Self {
+        Self {
+            treasure_maps: treasure_maps.to_vec(),
+        }
+    }
+
+    /// Returns the treasure maps in the archive, in the order they were added.
+    pub fn treasure_maps(&self) -> &[TreasureMap] {
+        &self.treasure_maps
+    }
+
+    /// Returns the treasure map for the given HRAC, if it is in the archive.
+    /// If there are several maps with the same HRAC, the first one is returned.
+    pub fn get(&self, hrac: &HRAC) -> Option<&TreasureMap> {
+        self.treasure_maps
+            .iter()
+            .find(|treasure_map| &treasure_map.hrac == hrac)
+    }
+
+    // The serialized form of the archive, indexed by HRAC.
+    fn entries(&self) -> Vec<ArchiveEntry> {
+        self.treasure_maps
+            .iter()
+            .map(|treasure_map| ArchiveEntry {
+                hrac: treasure_map.hrac,
+                treasure_map: treasure_map.to_bytes(),
+            })
+            .collect()
+    }
+
+    fn from_entries(entries: &[ArchiveEntry]) -> Result<Self, String> {
+        let treasure_maps = entries
+            .iter()
+            .map(|entry| {
+                let treasure_map = TreasureMap::from_bytes(&entry.treasure_map)
+                    .map_err(|err| format!("Failed to deserialize a treasure map: {}", err))?;
+                if treasure_map.hrac != entry.hrac {
+                    return Err("The HRAC in the index does not match the treasure map".into());
+                }
+                Ok(treasure_map)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { treasure_maps })
+    }
+}
+
+// The archive is serialized as its indexed entries in any format,
+// so that the HRACs are checked on deserialization regardless of the format.
+
+impl Serialize for TreasureMapArchive {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.entries().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TreasureMapArchive {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<ArchiveEntry>::deserialize(deserializer)?;
+        Self::from_entries(&entries).map_err(de::Error::custom)
+    }
+}
+
+/// An in-memory index of treasure maps by their HRACs.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct TreasureMapStore {
+    treasure_maps: BTreeMap<HRAC, TreasureMap>,
+}
+
+impl TreasureMapStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a treasure map under its HRAC,
+    /// returning the previously stored map with the same HRAC, if any.
+    pub fn insert(&mut self, treasure_map: TreasureMap) -> Option<TreasureMap> {
+        self.treasure_maps.insert(treasure_map.hrac, treasure_map)
+    }
+
+    /// Returns the treasure map for the given HRAC, if it is in the store.
+    pub fn get(&self, hrac: &HRAC) -> Option<&TreasureMap> {
+        self.treasure_maps.get(hrac)
+    }
+
+    /// Removes and returns the treasure map for the given HRAC, if it is in the store.
+    pub fn remove(&mut self, hrac: &HRAC) -> Option<TreasureMap> {
+        self.treasure_maps.remove(hrac)
+    }
+
+    /// Returns `true` if there is a treasure map for the given HRAC in the store.
+    pub fn contains(&self, hrac: &HRAC) -> bool {
+        self.treasure_maps.contains_key(hrac)
+    }
+
+    /// Returns the number of treasure maps in the store.
+    pub fn len(&self) -> usize {
+        self.treasure_maps.len()
+    }
+
+    /// Returns `true` if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.treasure_maps.is_empty()
+    }
+}
+
+impl<'a> ProtocolObjectInner<'a> for TreasureMapArchive {
+    fn brand() -> [u8; 4] {
+        *b"TMAr"
+    }
+
+    fn version() -> (u16, u16) {
+        (1, 0)
+    }
+
+    fn unversioned_to_bytes(&self) -> Box<[u8]> {
+        messagepack_serialize(&self)
+    }
+
+    fn unversioned_from_bytes(minor_version: u16, bytes: &[u8]) -> Option<Result<Self, String>> {
+        if minor_version == 0 {
+            Some(messagepack_deserialize(bytes))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> ProtocolObject<'a> for TreasureMapArchive {}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+
+    use umbral_pre::{generate_kfrags, SecretKey, Signer};
+
+    use super::{encrypt_kfrags_parallel, encrypt_kfrags_serial};
+    use crate::{Address, HRAC};
+
+    #[test]
+    fn parallel_encryption_matches_serial() {
+        // `EncryptedKeyFrag::new()` is randomized, so the serial and the parallel results
+        // cannot be compared byte by byte; instead, each destination is decrypted and compared.
+        let publisher_sk = SecretKey::random();
+        let signer = Signer::new(publisher_sk.clone());
+        let receiving_pk = SecretKey::random().public_key();
+        let hrac = HRAC::new(&publisher_sk.public_key(), &receiving_pk, b"label");
+
+        let shares = 50;
+        let vkfrags = generate_kfrags(&publisher_sk, &receiving_pk, &signer, 2, shares, true, true);
+        let ursula_sks: Vec<SecretKey> = (0..shares).map(|_| SecretKey::random()).collect();
+
+        let mut ursula_sks_by_address = BTreeMap::new();
+        let mut assigned_kfrags = Vec::new();
+        for (i, (ursula_sk, vkfrag)) in ursula_sks.iter().zip(vkfrags.iter()).enumerate() {
+            let mut address_bytes = [0u8; Address::SIZE];
+            address_bytes[0] = i as u8;
+            let address = Address::new(&address_bytes);
+            ursula_sks_by_address.insert(address, ursula_sk);
+            assigned_kfrags.push((address, (ursula_sk.public_key(), vkfrag.clone())));
+        }
+
+        let serial: BTreeMap<_, _> = encrypt_kfrags_serial(&signer, &hrac, assigned_kfrags.clone())
+            .into_iter()
+            .collect();
+        let parallel: BTreeMap<_, _> = encrypt_kfrags_parallel(&signer, &hrac, assigned_kfrags)
+            .into_iter()
+            .collect();
+
+        assert_eq!(serial.len(), shares);
+        assert!(serial.keys().eq(parallel.keys()));
+
+        let publisher_vk = publisher_sk.public_key();
+        for (address, ursula_sk) in ursula_sks_by_address.iter() {
+            let serial_kfrag = serial[address]
+                .decrypt(ursula_sk, &hrac, &publisher_vk)
+                .unwrap();
+            let parallel_kfrag = parallel[address]
+                .decrypt(ursula_sk, &hrac, &publisher_vk)
+                .unwrap();
+            assert_eq!(serial_kfrag, parallel_kfrag);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -6,6 +7,12 @@ use core::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    EncryptedKeyFrag, EncryptedTreasureMap, MessageKit, MetadataRequest, MetadataResponse,
+    MetadataResponsePayload, NodeMetadata, NodeMetadataPayload, ReencryptionRequest,
+    ReencryptionResponse, RetrievalKit, RevocationOrder, TreasureMap, TreasureMapArchive,
+};
+
 pub(crate) fn messagepack_serialize<T>(obj: &T) -> Box<[u8]>
 where
     T: Serialize,
@@ -23,6 +30,68 @@ where
         .expect("Error serializing into MessagePack")
 }
 
+// Same as `messagepack_serialize()`, but appends the result to an existing buffer.
+pub(crate) fn messagepack_serialize_into<T>(obj: &T, buffer: &mut Vec<u8>)
+where
+    T: Serialize,
+{
+    rmp_serde::encode::write(buffer, obj).expect("Error serializing into MessagePack")
+}
+
+/// Returns the length of a bytestring of length `len` serialized with MessagePack.
+pub(crate) fn messagepack_bytes_len(len: usize) -> usize {
+    // See the "bin" format family in the MessagePack specification.
+    let prefix_len = if len <= u8::MAX as usize {
+        2
+    } else if len <= u16::MAX as usize {
+        3
+    } else {
+        5
+    };
+    prefix_len + len
+}
+
+/// Returns the length of an unsigned integer serialized with MessagePack.
+pub(crate) fn messagepack_uint_len(value: u64) -> usize {
+    // See the "int" format family in the MessagePack specification;
+    // the most compact representation is always used.
+    if value < 0x80 {
+        1
+    } else if value <= u8::MAX as u64 {
+        2
+    } else if value <= u16::MAX as u64 {
+        3
+    } else if value <= u32::MAX as u64 {
+        5
+    } else {
+        9
+    }
+}
+
+/// Returns the length of the header of a MessagePack map with `len` entries.
+pub(crate) fn messagepack_map_header_len(len: usize) -> usize {
+    // See the "map" format family in the MessagePack specification.
+    if len < 16 {
+        1
+    } else if len <= u16::MAX as usize {
+        3
+    } else {
+        5
+    }
+}
+
+/// Returns the length of the header of an array with `len` elements serialized with MessagePack.
+pub(crate) fn messagepack_array_header_len(len: usize) -> usize {
+    // See the "array" format family in the MessagePack specification.
+    if len < 16 {
+        1
+    } else if len <= u16::MAX as usize {
+        3
+    } else {
+        5
+    }
+}
+
 pub(crate) fn messagepack_deserialize<'a, T>(bytes: &'a [u8]) -> Result<T, String>
 where
     T: Deserialize<'a>,
@@ -30,6 +99,10 @@ where
     rmp_serde::from_read_ref(bytes).map_err(|err| format!("{}", err))
 }
 
+/// The length of the header (the brand and the version)
+/// prepended to every serialized protocol object.
+pub const HEADER_SIZE: usize = 8;
+
 struct ProtocolObjectHeader {
     brand: [u8; 4],
     major_version: u16,
@@ -66,27 +139,47 @@ impl ProtocolObjectHeader {
     }
 }
 
+/// Errors that can happen during object deserialization.
 #[derive(Debug)]
 pub enum DeserializationError {
+    /// The bytestring is too short to contain the object.
     TooShort {
+        /// The minimum expected length.
         expected: usize,
+        /// The received length.
         received: usize,
     },
+    /// The brand in the header does not match the expected one.
     IncorrectHeader {
+        /// The expected brand.
         expected: [u8; 4],
+        /// The received brand.
         received: [u8; 4],
     },
+    /// The major version in the header differs from the supported one.
     MajorVersionMismatch {
+        /// The supported major version.
         expected: u16,
+        /// The received major version.
         received: u16,
     },
+    /// The minor version in the header is newer than the supported one.
     UnsupportedMinorVersion {
+        /// The latest supported minor version.
         expected: u16,
+        /// The received minor version.
         received: u16,
     },
+    /// The payload could not be deserialized.
     BadPayload {
+        /// The error message from the payload deserializer.
         error_msg: String,
     },
+    /// The brand in the header does not correspond to any known object.
+    UnknownBrand {
+        /// The received brand.
+        received: [u8; 4],
+    },
 }
 
 impl fmt::Display for DeserializationError {
@@ -115,6 +208,7 @@ impl fmt::Display for DeserializationError {
             Self::BadPayload { error_msg } => {
                 write!(f, "payload deserialization failed: {}", error_msg)
             }
+            Self::UnknownBrand { received } => write!(f, "unknown brand: {:?}", received),
         }
     }
 }
@@ -135,7 +229,20 @@ pub trait ProtocolObjectInner<'a>: Serialize + Deserialize<'a> {
 
     fn unversioned_to_bytes(&self) -> Box<[u8]>;
 
+    // Appends the result of `unversioned_to_bytes()` to `buffer`.
+    // The default implementation serializes `self` directly,
+    // so it has to be overridden if `unversioned_to_bytes()` does something else.
+    fn unversioned_to_bytes_into(&self, buffer: &mut Vec<u8>) {
+        messagepack_serialize_into(self, buffer)
+    }
+
     fn unversioned_from_bytes(minor_version: u16, bytes: &'a [u8]) -> Option<Result<Self, String>>;
+
+    // Checks the invariants of a deserialized object that the payload format cannot express.
+    // Called by both `from_bytes()` and `from_bytes_bincode()`.
+    fn validate_decoded(&self) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 /// This is a versioned protocol object.
@@ -160,49 +267,380 @@ pub trait ProtocolObject<'a>: ProtocolObjectInner<'a> {
         result.into_boxed_slice()
     }
 
+    /// Serializes the object, appending the result to `buffer`.
+    ///
+    /// Produces the same bytes as [`to_bytes`](`Self::to_bytes`),
+    /// but allows one to reuse the allocated memory between calls.
+    fn to_bytes_into(&self, buffer: &mut Vec<u8>) {
+        buffer.extend(&ProtocolObjectHeader::from_type::<Self>().to_bytes());
+        Self::unversioned_to_bytes_into(self, buffer);
+    }
+
     /// Attempts to deserialize the object.
     fn from_bytes(bytes: &'a [u8]) -> Result<Self, DeserializationError> {
-        if bytes.len() < 8 {
-            return Err(DeserializationError::TooShort {
-                expected: 8,
-                received: bytes.len(),
-            });
-        }
-        let mut header_bytes = [0u8; 8];
-        header_bytes.copy_from_slice(&bytes[..8]);
-        let header = ProtocolObjectHeader::from_bytes(&header_bytes);
+        let header = check_header::<Self>(bytes)?;
+        let reference_header = ProtocolObjectHeader::from_type::<Self>();
 
+        let result = match Self::unversioned_from_bytes(header.minor_version, &bytes[HEADER_SIZE..])
+        {
+            Some(result) => result,
+            // The type must support all minor versions below or equal to the current one,
+            // otherwise it should be the major version change.
+            // This is a bug, but we report it as an error instead of panicking,
+            // so that deserialization never panics regardless of the input.
+            None => {
+                return Err(DeserializationError::UnsupportedMinorVersion {
+                    expected: reference_header.minor_version,
+                    received: header.minor_version,
+                })
+            }
+        };
+
+        result
+            .and_then(|obj| obj.validate_decoded().map(|_| obj))
+            .map_err(|msg| DeserializationError::BadPayload { error_msg: msg })
+    }
+
+    /// Serializes the object with the same header as [`to_bytes`](`Self::to_bytes`),
+    /// but using `bincode` for the payload.
+    ///
+    /// This format is only intended for communication between Rust applications
+    /// built with the same version of this crate; other implementations will not read it.
+    #[cfg(feature = "bincode")]
+    fn to_bytes_bincode(&self) -> Box<[u8]> {
+        let header_bytes = ProtocolObjectHeader::from_type::<Self>().to_bytes();
+        // Same as in `messagepack_serialize()`, a serialization error indicates a bug.
+        let unversioned_bytes = bincode::serialize(self).expect("Error serializing into bincode");
+
+        let mut result = Vec::with_capacity(header_bytes.len() + unversioned_bytes.len());
+        result.extend(&header_bytes);
+        result.extend(unversioned_bytes.iter());
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to deserialize the object serialized with
+    /// [`to_bytes_bincode`](`Self::to_bytes_bincode`).
+    ///
+    /// Since `bincode` is not self-describing, the object must have exactly
+    /// the current version (older minor versions are not supported).
+    #[cfg(feature = "bincode")]
+    fn from_bytes_bincode(bytes: &'a [u8]) -> Result<Self, DeserializationError> {
+        let header = check_header::<Self>(bytes)?;
         let reference_header = ProtocolObjectHeader::from_type::<Self>();
 
-        if header.brand != reference_header.brand {
-            return Err(DeserializationError::IncorrectHeader {
-                expected: reference_header.brand,
-                received: header.brand,
+        if header.minor_version != reference_header.minor_version {
+            return Err(DeserializationError::UnsupportedMinorVersion {
+                expected: reference_header.minor_version,
+                received: header.minor_version,
             });
         }
 
-        if header.major_version != reference_header.major_version {
-            return Err(DeserializationError::MajorVersionMismatch {
-                expected: reference_header.major_version,
-                received: header.major_version,
+        bincode::deserialize(&bytes[HEADER_SIZE..])
+            .map_err(|err| format!("{}", err))
+            .and_then(|obj: Self| obj.validate_decoded().map(|_| obj))
+            .map_err(|msg| DeserializationError::BadPayload { error_msg: msg })
+    }
+}
+
+// Checks the header of a serialized object against the one expected for `T`,
+// and returns it.
+fn check_header<'a, T>(bytes: &[u8]) -> Result<ProtocolObjectHeader, DeserializationError>
+where
+    T: ProtocolObjectInner<'a>,
+{
+    if bytes.len() < HEADER_SIZE {
+        return Err(DeserializationError::TooShort {
+            expected: HEADER_SIZE,
+            received: bytes.len(),
+        });
+    }
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    header_bytes.copy_from_slice(&bytes[..HEADER_SIZE]);
+    let header = ProtocolObjectHeader::from_bytes(&header_bytes);
+
+    let reference_header = ProtocolObjectHeader::from_type::<T>();
+
+    if header.brand != reference_header.brand {
+        return Err(DeserializationError::IncorrectHeader {
+            expected: reference_header.brand,
+            received: header.brand,
+        });
+    }
+
+    if header.major_version != reference_header.major_version {
+        return Err(DeserializationError::MajorVersionMismatch {
+            expected: reference_header.major_version,
+            received: header.major_version,
+        });
+    }
+
+    if header.minor_version > reference_header.minor_version {
+        return Err(DeserializationError::UnsupportedMinorVersion {
+            expected: reference_header.minor_version,
+            received: header.minor_version,
+        });
+    }
+
+    Ok(header)
+}
+
+fn read_be_uint(bytes: &[u8], offset: usize, size: usize) -> Option<usize> {
+    let field = bytes.get(offset..offset.checked_add(size)?)?;
+    Some(
+        field
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | (*byte as usize)),
+    )
+}
+
+// Protocol objects are only a few levels deep; the limit protects from stack overflows
+// on maliciously crafted inputs.
+const MAX_MESSAGEPACK_DEPTH: usize = 32;
+
+/// Returns the length of the MessagePack value at the start of `bytes`,
+/// or `None` if it is malformed, truncated, or nested too deeply.
+///
+/// The value is not decoded, only its nested structure is walked.
+fn messagepack_value_len(bytes: &[u8], depth: usize) -> Option<usize> {
+    if depth > MAX_MESSAGEPACK_DEPTH {
+        return None;
+    }
+
+    let marker = *bytes.first()?;
+
+    // (header length, payload length in bytes, number of nested values)
+    let (header_len, payload_len, nested_values): (usize, usize, usize) = match marker {
+        // positive fixint, nil, false, true, negative fixint
+        0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => (1, 0, 0),
+        // fixmap
+        0x80..=0x8f => (1, 0, 2 * (marker & 0x0f) as usize),
+        // fixarray
+        0x90..=0x9f => (1, 0, (marker & 0x0f) as usize),
+        // fixstr
+        0xa0..=0xbf => (1, (marker & 0x1f) as usize, 0),
+        // bin 8/16/32, str 8/16/32
+        0xc4 | 0xd9 => (2, read_be_uint(bytes, 1, 1)?, 0),
+        0xc5 | 0xda => (3, read_be_uint(bytes, 1, 2)?, 0),
+        0xc6 | 0xdb => (5, read_be_uint(bytes, 1, 4)?, 0),
+        // ext 8/16/32 (the length is followed by a type byte)
+        0xc7 => (3, read_be_uint(bytes, 1, 1)?, 0),
+        0xc8 => (4, read_be_uint(bytes, 1, 2)?, 0),
+        0xc9 => (6, read_be_uint(bytes, 1, 4)?, 0),
+        // float 32/64
+        0xca => (1, 4, 0),
+        0xcb => (1, 8, 0),
+        // uint 8/16/32/64, int 8/16/32/64
+        0xcc | 0xd0 => (1, 1, 0),
+        0xcd | 0xd1 => (1, 2, 0),
+        0xce | 0xd2 => (1, 4, 0),
+        0xcf | 0xd3 => (1, 8, 0),
+        // fixext 1/2/4/8/16 (a type byte and the data)
+        0xd4 => (2, 1, 0),
+        0xd5 => (2, 2, 0),
+        0xd6 => (2, 4, 0),
+        0xd7 => (2, 8, 0),
+        0xd8 => (2, 16, 0),
+        // array 16/32
+        0xdc => (3, 0, read_be_uint(bytes, 1, 2)?),
+        0xdd => (5, 0, read_be_uint(bytes, 1, 4)?),
+        // map 16/32
+        // (the lengths come from untrusted data, so the arithmetic is checked)
+        0xde => (3, 0, read_be_uint(bytes, 1, 2)?.checked_mul(2)?),
+        0xdf => (5, 0, read_be_uint(bytes, 1, 4)?.checked_mul(2)?),
+        // 0xc1 is never used
+        0xc1 => return None,
+    };
+
+    let mut len = header_len.checked_add(payload_len)?;
+    if len > bytes.len() {
+        return None;
+    }
+    for _ in 0..nested_values {
+        len = len.checked_add(messagepack_value_len(&bytes[len..], depth + 1)?)?;
+    }
+    Some(len)
+}
+
+// The brand and the serialized bytes (including the header) of a protocol object.
+type ObjectSpan<'a> = (&'a [u8; 4], &'a [u8]);
+
+/// Splits a concatenation of serialized protocol objects
+/// into the brands and the byte spans (including the headers) of each object,
+/// without deserializing them.
+///
+/// Each span can be passed to `from_bytes()` of the type corresponding to its brand.
+pub fn split_objects(data: &[u8]) -> Result<Vec<ObjectSpan<'_>>, DeserializationError> {
+    let mut objects = Vec::new();
+    let mut remainder = data;
+    while !remainder.is_empty() {
+        if remainder.len() < HEADER_SIZE {
+            return Err(DeserializationError::TooShort {
+                expected: HEADER_SIZE,
+                received: remainder.len(),
             });
         }
 
-        if header.minor_version > reference_header.minor_version {
-            return Err(DeserializationError::UnsupportedMinorVersion {
-                expected: reference_header.minor_version,
-                received: header.minor_version,
+        let object_len = messagepack_value_len(&remainder[HEADER_SIZE..], 0)
+            .and_then(|payload_len| HEADER_SIZE.checked_add(payload_len))
+            .ok_or_else(|| DeserializationError::BadPayload {
+                error_msg: "malformed or truncated MessagePack payload".into(),
+            })?;
+
+        let (object, rest) = remainder.split_at(object_len);
+        let brand = object[..4]
+            .try_into()
+            .expect("the object contains at least the header");
+        objects.push((brand, object));
+        remainder = rest;
+    }
+    Ok(objects)
+}
+
+/// One of the protocol objects, as returned by [`try_decode_any`].
+#[derive(PartialEq, Debug)]
+pub enum DecodedObject {
+    /// A [`MessageKit`].
+    MessageKit(MessageKit),
+    /// An [`EncryptedKeyFrag`].
+    EncryptedKeyFrag(EncryptedKeyFrag),
+    /// A [`TreasureMap`].
+    TreasureMap(TreasureMap),
+    /// An [`EncryptedTreasureMap`].
+    EncryptedTreasureMap(EncryptedTreasureMap),
+    /// A [`TreasureMapArchive`].
+    TreasureMapArchive(TreasureMapArchive),
+    /// A [`ReencryptionRequest`].
+    ReencryptionRequest(ReencryptionRequest),
+    /// A [`ReencryptionResponse`].
+    ReencryptionResponse(ReencryptionResponse),
+    /// A [`RetrievalKit`].
+    RetrievalKit(RetrievalKit),
+    /// A [`RevocationOrder`].
+    RevocationOrder(RevocationOrder),
+    /// A [`NodeMetadataPayload`].
+    NodeMetadataPayload(NodeMetadataPayload),
+    /// A [`NodeMetadata`].
+    NodeMetadata(NodeMetadata),
+    /// A [`MetadataRequest`].
+    MetadataRequest(MetadataRequest),
+    /// A [`MetadataResponsePayload`].
+    MetadataResponsePayload(MetadataResponsePayload),
+    /// A [`MetadataResponse`].
+    MetadataResponse(MetadataResponse),
+}
+
+fn decode_as<'a, T>(
+    data: &'a [u8],
+    wrap: fn(T) -> DecodedObject,
+) -> Result<DecodedObject, DeserializationError>
+where
+    T: ProtocolObject<'a>,
+{
+    T::from_bytes(data).map(wrap)
+}
+
+/// Attempts to deserialize any of the public protocol objects, based on the brand in the header.
+///
+/// Never panics, regardless of the input.
+pub fn try_decode_any(data: &[u8]) -> Result<DecodedObject, DeserializationError> {
+    if data.len() < HEADER_SIZE {
+        return Err(DeserializationError::TooShort {
+            expected: HEADER_SIZE,
+            received: data.len(),
+        });
+    }
+    let brand = [data[0], data[1], data[2], data[3]];
+
+    if brand == <MessageKit as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::MessageKit)
+    } else if brand == <EncryptedKeyFrag as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::EncryptedKeyFrag)
+    } else if brand == <TreasureMap as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::TreasureMap)
+    } else if brand == <EncryptedTreasureMap as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::EncryptedTreasureMap)
+    } else if brand == <TreasureMapArchive as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::TreasureMapArchive)
+    } else if brand == <ReencryptionRequest as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::ReencryptionRequest)
+    } else if brand == <ReencryptionResponse as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::ReencryptionResponse)
+    } else if brand == <RetrievalKit as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::RetrievalKit)
+    } else if brand == <RevocationOrder as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::RevocationOrder)
+    } else if brand == <NodeMetadataPayload as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::NodeMetadataPayload)
+    } else if brand == <NodeMetadata as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::NodeMetadata)
+    } else if brand == <MetadataRequest as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::MetadataRequest)
+    } else if brand == <MetadataResponsePayload as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::MetadataResponsePayload)
+    } else if brand == <MetadataResponse as ProtocolObjectInner<'_>>::brand() {
+        decode_as(data, DecodedObject::MetadataResponse)
+    } else {
+        Err(DeserializationError::UnknownBrand { received: brand })
+    }
+}
+
+// `(major, minor)`
+type Version = (u16, u16);
+
+/// Records the versions of serialized objects received from peers,
+/// to detect nodes running older (or newer) versions of the protocol.
+#[derive(Debug, Clone, Default)]
+pub struct VersionObserver {
+    // Brand -> (the minimum seen version, the maximum seen version)
+    versions: BTreeMap<[u8; 4], (Version, Version)>,
+}
+
+impl VersionObserver {
+    /// Creates an observer with no recorded versions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the brand and the version from the header of a serialized object.
+    ///
+    /// Only the header is read, the object is not deserialized.
+    pub fn observe(&mut self, bytes: &[u8]) -> Result<(), DeserializationError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(DeserializationError::TooShort {
+                expected: HEADER_SIZE,
+                received: bytes.len(),
             });
         }
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        header_bytes.copy_from_slice(&bytes[..HEADER_SIZE]);
+        let header = ProtocolObjectHeader::from_bytes(&header_bytes);
 
-        let result = match Self::unversioned_from_bytes(header.minor_version, &bytes[8..]) {
-            Some(result) => result,
-            // The type must support all minor versions below or equal to the current one,
-            // otherwise it should be the major version change.
-            // This is a bug, so we panic here.
-            None => panic!("minor version {} is not supported", header.minor_version),
-        };
+        let version = (header.major_version, header.minor_version);
+        self.versions
+            .entry(header.brand)
+            .and_modify(|(min_seen, max_seen)| {
+                if version < *min_seen {
+                    *min_seen = version;
+                }
+                if version > *max_seen {
+                    *max_seen = version;
+                }
+            })
+            .or_insert((version, version));
+        Ok(())
+    }
+
+    /// Returns the minimum version `(major, minor)` seen for the brand.
+    pub fn min_seen(&self, brand: &[u8; 4]) -> Option<(u16, u16)> {
+        self.versions
+            .get(brand)
+            .map(|(min_seen, _max_seen)| *min_seen)
+    }
 
-        result.map_err(|msg| DeserializationError::BadPayload { error_msg: msg })
+    /// Returns the maximum version `(major, minor)` seen for the brand.
+    pub fn max_seen(&self, brand: &[u8; 4]) -> Option<(u16, u16)> {
+        self.versions
+            .get(brand)
+            .map(|(_min_seen, max_seen)| *max_seen)
     }
 }